@@ -0,0 +1,78 @@
+#![no_main]
+
+use jsonparser::parser::{parse, JsonVal, MapType};
+use jsonparser::tokenizer::Tokenizer;
+use jsonparser::{borrowed, bytes, simd, tape};
+use libfuzzer_sys::fuzz_target;
+
+// The parsers over bytes must accept the same input as `parser::parse`, with
+// the same values, down to the variant of every number. They report their
+// errors like `bytes::parse`, which differs from `parser::parse` in kinds and
+// locations, except `simd`, which gives the errors of `parser::parse`.
+fuzz_target!(|data: &[u8]| {
+    let parsed = std::str::from_utf8(data)
+        .ok()
+        .map(|input| parse(Tokenizer::from_str(input)));
+    let expected = parsed.as_ref().and_then(|res| res.as_ref().ok());
+    let reference = bytes::parse(data);
+    assert_eq!(reference.as_ref().ok().map(debug), expected.map(debug));
+
+    let tape = tape::Document::parse(data).map(|doc| JsonVal::from(&doc));
+    assert_eq!(outcome(&tape), outcome(&reference), "tape");
+    let simd_tape = simd::parse_tape(data).map(|doc| JsonVal::from(&doc));
+    for (name, res) in [("simd", simd::parse(data)), ("simd tape", simd_tape)] {
+        match &parsed {
+            Some(parsed) => assert_eq!(outcome(&res), outcome(parsed), "{}", name),
+            None => assert_eq!(outcome(&res), outcome(&reference), "{}", name),
+        }
+    }
+
+    // Strings are decoded, so lone surrogates are rejected and the escapes
+    // of the others are normalized
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let surrogates = input.to_ascii_lowercase().contains("\\ud");
+    match (borrowed::parse(input), &reference) {
+        (Ok(val), Ok(expected)) => {
+            assert_eq!(debug(&JsonVal::from(val)), debug(&decoded(expected)))
+        }
+        (Ok(_), Err(err)) => panic!("Expected {}", err),
+        (Err(err), Ok(_)) => assert!(surrogates, "{}", err),
+        (Err(err), Err(expected)) => {
+            if !surrogates {
+                assert_eq!(err.to_string(), expected.to_string());
+            }
+        }
+    }
+});
+
+fn debug(val: &JsonVal) -> String {
+    format!("{:?}", val)
+}
+
+fn outcome<E: std::fmt::Display>(res: &Result<JsonVal, E>) -> Result<String, String> {
+    res.as_ref().map(debug).map_err(|err| err.to_string())
+}
+
+// `val` with its strings normalized like `borrowed` does
+fn decoded(val: &JsonVal) -> JsonVal {
+    let string = |s: &str| {
+        let text = format!("\"{}\"", s);
+        let val = borrowed::parse(&text).expect("Expected a valid string");
+        match JsonVal::from(val) {
+            JsonVal::String(s) => s,
+            _ => unreachable!(),
+        }
+    };
+    match val {
+        JsonVal::String(s) => JsonVal::String(string(s)),
+        JsonVal::Array(arr) => JsonVal::Array(arr.iter().map(decoded).collect()),
+        JsonVal::Object(obj) => JsonVal::Object(
+            obj.iter()
+                .map(|(k, v)| (string(k), decoded(v)))
+                .collect::<MapType<_, _>>(),
+        ),
+        _ => val.clone(),
+    }
+}
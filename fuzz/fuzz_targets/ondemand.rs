@@ -0,0 +1,62 @@
+#![no_main]
+
+use jsonparser::ondemand::{Document, Value};
+use jsonparser::parser::parse;
+use jsonparser::tokenizer::Tokenizer;
+use jsonparser::JsonVal;
+use libfuzzer_sys::fuzz_target;
+
+// Navigating must not panic, whatever the input, and on valid input must give
+// the same values as `parser::parse`
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let doc = Document::from_str(input);
+    walk(doc.root(), 0);
+    let _ = doc.get("a");
+    let _ = doc.get_first("a");
+    let _ = doc.at(1);
+
+    let Ok(expected) = parse(Tokenizer::from_str(input)) else {
+        return;
+    };
+    let debug = |val: &JsonVal| format!("{:?}", val);
+    let root = doc.root().to_json_val().expect("Expected a valid value");
+    assert_eq!(debug(&root), debug(&expected));
+    if let JsonVal::Array(arr) = &expected {
+        let elements: Vec<_> = doc
+            .elements()
+            .map(|val| debug(&val.unwrap().to_json_val().unwrap()))
+            .collect();
+        assert_eq!(elements, arr.iter().map(debug).collect::<Vec<_>>());
+        for (i, val) in arr.iter().enumerate() {
+            assert_eq!(
+                debug(&doc.at(i).unwrap().to_json_val().unwrap()),
+                debug(val)
+            );
+        }
+        assert!(doc.at(arr.len()).is_err());
+    }
+    if let JsonVal::Object(obj) = &expected {
+        for (key, val) in obj {
+            assert_eq!(
+                debug(&doc.get(key).unwrap().to_json_val().unwrap()),
+                debug(val)
+            );
+            assert!(doc.get_first(key).is_ok());
+        }
+    }
+});
+
+fn walk(val: Value, depth: usize) {
+    let _ = val.as_str();
+    let _ = val.to_json_val();
+    let _ = val.get("a");
+    let _ = val.get_first("a");
+    if depth < 8 {
+        for element in val.elements().take(8).flatten() {
+            walk(element, depth + 1);
+        }
+    }
+}
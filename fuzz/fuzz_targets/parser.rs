@@ -0,0 +1,31 @@
+#![no_main]
+
+use jsonparser::parser::parse;
+use jsonparser::tokenizer::Tokenizer;
+use jsonparser::Options;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let options = [
+        Options::default(),
+        Options {
+            arbitrary_precision: true,
+            ..Default::default()
+        },
+        Options {
+            comments: true,
+            trailing_commas: true,
+            ..Default::default()
+        },
+        Options {
+            json5: true,
+            ..Default::default()
+        },
+    ];
+    for options in options {
+        let _ = parse(Tokenizer::from_str(input).with_options(options));
+    }
+});
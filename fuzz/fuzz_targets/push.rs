@@ -0,0 +1,42 @@
+#![no_main]
+
+use jsonparser::parser::parse;
+use jsonparser::push::{Event, PushParser};
+use jsonparser::tokenizer::Tokenizer;
+use libfuzzer_sys::fuzz_target;
+
+// However the input is cut into chunks, the push parser must accept the same
+// input as `parser::parse`, with the same value. The first byte gives the size
+// of the chunks.
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, data)) = data.split_first() else {
+        return;
+    };
+    let expected = std::str::from_utf8(data)
+        .ok()
+        .and_then(|input| parse(Tokenizer::from_str(input)).ok());
+
+    let mut parser = PushParser::new();
+    let mut val = None;
+    let mut run = || {
+        for chunk in data.chunks(usize::from(size).max(1)) {
+            parser.feed(chunk);
+            if let Some(v) = parser.value()? {
+                assert!(val.replace(v).is_none());
+            }
+        }
+        parser.finish();
+        if let Some(v) = parser.value()? {
+            assert!(val.replace(v).is_none());
+        }
+        // Nothing can follow the value
+        while parser.next_event()? != Event::End {}
+        Ok::<_, jsonparser::tokenizer::ParsingError>(())
+    };
+    let ok = run().is_ok();
+    let got = val.filter(|_| ok);
+    assert_eq!(
+        got.map(|val| format!("{:?}", val)),
+        expected.map(|val| format!("{:?}", val))
+    );
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use jsonparser::parser::parse_value;
+use jsonparser::tokenizer::Tokenizer;
+use jsonparser::NonFinite;
+use libfuzzer_sys::fuzz_target;
+
+// Whatever parses must be written back as JSON that parses to the same value
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let parse = |s: &str| parse_value(Tokenizer::from_str(s));
+    let Ok(val) = parse(input) else {
+        return;
+    };
+    // Floats too big for `f64`, like `1e400`, are infinite and written as `null`
+    if val.to_string_with(NonFinite::Error).is_err() {
+        return;
+    }
+    for text in [val.to_string(), val.to_compact_string()] {
+        match parse(&text) {
+            Ok(reparsed) => assert_eq!(reparsed, val, "{}", text),
+            Err(err) => panic!("{} for {}", err, text),
+        }
+    }
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use jsonparser::tokenizer::Tokenizer;
+use jsonparser::Options;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let json5 = Options {
+        json5: true,
+        ..Default::default()
+    };
+    for options in [Options::default(), json5] {
+        let mut tokenizer = Tokenizer::from_str(input).with_options(options);
+        // Every call consumes at least one character until the end
+        for _ in 0..=input.len() {
+            if tokenizer.next_token().is_err() {
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,123 @@
+use crate::parser::JsonVal;
+use crate::push::{Event, PushParser};
+use crate::tokenizer::{Limits, ParsingError};
+use core::fmt::Display;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+// Parsing from a tokio `AsyncRead`, on top of `push::PushParser`, so values can
+// be parsed as the data arrives instead of after buffering all of it
+
+const BUF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(std::io::Error),
+    Parsing(ParsingError),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "{}", err),
+            ReadError::Parsing(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+// Reads until the end of `reader`, which must contain a single value, like
+// `parser::parse_value`, with the default `Limits`
+pub async fn parse_async(reader: impl AsyncRead + Unpin) -> Result<JsonVal, ReadError> {
+    parse_async_with_limits(reader, Limits::default()).await
+}
+
+// Like `parse_async`, failing as soon as the data read goes over `limits`
+pub async fn parse_async_with_limits(
+    mut reader: impl AsyncRead + Unpin,
+    limits: Limits,
+) -> Result<JsonVal, ReadError> {
+    let mut parser = PushParser::new().with_limits(limits);
+    let mut buf = vec![0; BUF_SIZE];
+    let mut val = None;
+    loop {
+        let len = reader.read(&mut buf).await.map_err(ReadError::Io)?;
+        if len == 0 {
+            parser.finish();
+        } else {
+            parser.feed(&buf[..len]);
+        }
+        match val {
+            None => val = parser.value().map_err(ReadError::Parsing)?,
+            // Only whitespace can follow the value
+            Some(_) => {
+                parser.next_event().map_err(ReadError::Parsing)?;
+            }
+        }
+        if len == 0 {
+            // Once finished the parser gives either the value or an error
+            return val.ok_or_else(|| ReadError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+    }
+}
+
+// The events of the value in `reader`, without `Event::NeedMoreData`. It ends
+// after the first error or at the end of the input. The default `Limits` apply.
+pub fn events<R: AsyncRead + Unpin>(reader: R) -> EventStream<R> {
+    events_with_limits(reader, Limits::default())
+}
+
+pub fn events_with_limits<R: AsyncRead + Unpin>(reader: R, limits: Limits) -> EventStream<R> {
+    EventStream {
+        reader,
+        parser: PushParser::new().with_limits(limits),
+        buf: vec![0; BUF_SIZE],
+        done: false,
+    }
+}
+
+pub struct EventStream<R> {
+    reader: R,
+    parser: PushParser,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> Stream for EventStream<R> {
+    type Item = Result<Event, ReadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match this.parser.next_event() {
+                Ok(Event::NeedMoreData) => {
+                    let mut buf = ReadBuf::new(&mut this.buf);
+                    match Pin::new(&mut this.reader).poll_read(cx, &mut buf) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Ok(())) if buf.filled().is_empty() => this.parser.finish(),
+                        Poll::Ready(Ok(())) => {
+                            let len = buf.filled().len();
+                            this.parser.feed(&this.buf[..len]);
+                        }
+                        Poll::Ready(Err(err)) => {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(ReadError::Io(err))));
+                        }
+                    }
+                }
+                Ok(Event::End) => this.done = true,
+                Ok(event) => return Poll::Ready(Some(Ok(event))),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(ReadError::Parsing(err))));
+                }
+            }
+        }
+    }
+}
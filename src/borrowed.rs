@@ -0,0 +1,403 @@
+use crate::bytes::{check_input_size, Counts};
+use crate::number::{parse_number, Number};
+use crate::parser::{JsonVal, MapType};
+use crate::tokenizer::{Limits, Loc, ParsingError, ParsingErrorKind};
+use std::borrow::Cow;
+
+// A value that borrows its strings from the input it was parsed from. Strings
+// are only copied when they contain escapes, which are decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValRef<'a> {
+    Null,
+    Number(Number),
+    String(Cow<'a, str>),
+    Boolean(bool),
+    Array(Vec<JsonValRef<'a>>),
+    Object(MapType<Cow<'a, str>, JsonValRef<'a>>),
+}
+
+// Strings in `JsonVal` keep their escapes, so decoded strings are escaped again.
+// Only the characters that have to be escaped are, which normalizes the rest:
+// `"\u00e9"` becomes `"é"` and `"\/"` becomes `"/"`. So with escapes like those
+// the result differs from `parser::parse` on the same input, though both
+// decode to the same text.
+impl From<JsonValRef<'_>> for JsonVal {
+    fn from(val: JsonValRef<'_>) -> Self {
+        match val {
+            JsonValRef::Null => JsonVal::Null,
+            JsonValRef::Number(num) => JsonVal::Number(num),
+            JsonValRef::String(s) => JsonVal::String(escape(&s)),
+            JsonValRef::Boolean(b) => JsonVal::Boolean(b),
+            JsonValRef::Array(arr) => JsonVal::Array(arr.into_iter().map(JsonVal::from).collect()),
+            JsonValRef::Object(obj) => JsonVal::Object(
+                obj.into_iter()
+                    .map(|(k, v)| (escape(&k), JsonVal::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Parses a value of any type, like `parser::parse_value`, without going
+// through the tokenizer so no token has to be allocated, with the default
+// `Limits`.
+pub fn parse(input: &str) -> Result<JsonValRef<'_>, ParsingError> {
+    parse_with_limits(input, Limits::default())
+}
+
+// Like `parse`, checking every one of `limits` like `bytes::parse_with_limits`
+pub fn parse_with_limits(input: &str, limits: Limits) -> Result<JsonValRef<'_>, ParsingError> {
+    check_input_size(input.as_bytes(), limits)?;
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        counts: Counts::new(limits),
+    };
+    let val = parser.parse_val()?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error(ParsingErrorKind::UnexpectedToken, parser.pos));
+    }
+    Ok(val)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    counts: Counts,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    // Locations are only needed for errors, so they are found from the byte
+    // offset instead of being tracked while parsing
+    fn error(&self, kind: ParsingErrorKind, pos: usize) -> ParsingError {
+        let before = &self.input[..pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        ParsingError {
+            kind,
+            loc: Loc {
+                col: before[line_start..].chars().count() + 1,
+                line: before.matches('\n').count() + 1,
+            },
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    // Skips the opening bracket of an array or object, checking the depth
+    fn enter(&mut self) -> Result<(), ParsingError> {
+        self.counts
+            .enter()
+            .map_err(|kind| self.error(kind, self.pos))?;
+        self.pos += 1;
+        Ok(())
+    }
+
+    // Counts the element or member at the next value, `len` including it
+    fn check_len(&mut self, len: usize, object: bool) -> Result<(), ParsingError> {
+        self.skip_whitespace();
+        self.counts
+            .len(len, object)
+            .map_err(|kind| self.error(kind, self.pos))
+    }
+
+    fn parse_val(&mut self) -> Result<JsonValRef<'a>, ParsingError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            Some(b'{') => {
+                self.enter()?;
+                let val = self.parse_object()?;
+                self.counts.leave();
+                Ok(val)
+            }
+            Some(b'[') => {
+                self.enter()?;
+                let val = self.parse_array()?;
+                self.counts.leave();
+                Ok(val)
+            }
+            _ => {
+                self.counts.node().map_err(|kind| self.error(kind, start))?;
+                self.parse_scalar()
+            }
+        }
+    }
+
+    fn parse_scalar(&mut self) -> Result<JsonValRef<'a>, ParsingError> {
+        use ParsingErrorKind::*;
+        let start = self.pos;
+        match self.peek() {
+            Some(b'"') => self.parse_string().map(JsonValRef::String),
+            Some(b'-' | b'0'..=b'9') => {
+                while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.peek() {
+                    self.pos += 1;
+                }
+                self.counts
+                    .number(self.pos - start)
+                    .map_err(|kind| self.error(kind, start))?;
+                parse_number(&self.input[start..self.pos])
+                    .map(JsonValRef::Number)
+                    .ok_or_else(|| self.error(InvalidToken, start))
+            }
+            Some(b) if b.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|b| b.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                match &self.input[start..self.pos] {
+                    "true" => Ok(JsonValRef::Boolean(true)),
+                    "false" => Ok(JsonValRef::Boolean(false)),
+                    "null" => Ok(JsonValRef::Null),
+                    _ => Err(self.error(InvalidToken, start)),
+                }
+            }
+            Some(b) if !b.is_ascii() => Err(self.error(UnsupportedToken, start)),
+            _ => Err(self.error(InvalidToken, start)),
+        }
+    }
+
+    // Skips the comma after an element, returning whether the container ended
+    fn parse_separator(&mut self, end: u8) -> Result<bool, ParsingError> {
+        use ParsingErrorKind::*;
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b',') => {
+                let comma = self.pos;
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(end) {
+                    Err(self.error(InvalidTrailingComma, comma))
+                } else {
+                    Ok(false)
+                }
+            }
+            Some(b) if b == end => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.error(MissingEndingComma, self.pos)),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValRef<'a>, ParsingError> {
+        let mut map = MapType::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValRef::Object(map));
+        }
+        // Duplicate keys count too, like in the tokenizer
+        let mut len = 0;
+        loop {
+            len += 1;
+            self.check_len(len, true)?;
+            if self.peek() != Some(b'"') {
+                return Err(self.error(ParsingErrorKind::UnexpectedToken, self.pos));
+            }
+            let ident = self.parse_string()?;
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Err(self.error(ParsingErrorKind::UnexpectedToken, self.pos));
+            }
+            self.pos += 1;
+            map.insert(ident, self.parse_val()?);
+            if self.parse_separator(b'}')? {
+                break Ok(JsonValRef::Object(map));
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValRef<'a>, ParsingError> {
+        let mut arr = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValRef::Array(arr));
+        }
+        loop {
+            self.check_len(arr.len() + 1, false)?;
+            arr.push(self.parse_val()?);
+            if self.parse_separator(b']')? {
+                break Ok(JsonValRef::Array(arr));
+            }
+        }
+    }
+
+    // Parses a string starting at its opening quote
+    fn parse_string(&mut self) -> Result<Cow<'a, str>, ParsingError> {
+        let quote = self.pos;
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.check_string(start, quote)?;
+                    self.pos += 1;
+                    return Ok(Cow::Borrowed(&self.input[start..self.pos - 1]));
+                }
+                Some(b'\\') => break,
+                Some(0x00..=0x1f) => {
+                    return Err(self.error(ParsingErrorKind::InvalidToken, self.pos))
+                }
+                Some(_) => self.pos += 1,
+                None => return Err(self.error(ParsingErrorKind::InvalidToken, quote)),
+            }
+        }
+
+        // Only strings with escapes get copied
+        let mut s = self.input[start..self.pos].to_string();
+        loop {
+            let chunk = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            s.push_str(&self.input[chunk..self.pos]);
+            match self.peek() {
+                Some(b'"') => {
+                    self.check_string(start, quote)?;
+                    self.pos += 1;
+                    return Ok(Cow::Owned(s));
+                }
+                Some(b'\\') => {
+                    let escape = self.pos;
+                    let invalid =
+                        |parser: &Self| parser.error(ParsingErrorKind::InvalidToken, escape);
+                    let kind = *self
+                        .input
+                        .as_bytes()
+                        .get(escape + 1)
+                        .ok_or_else(|| invalid(self))?;
+                    self.pos += 2;
+                    let c = match kind {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.parse_unicode_escape().ok_or_else(|| invalid(self))?,
+                        _ => return Err(invalid(self)),
+                    };
+                    s.push(c);
+                }
+                Some(_) => return Err(self.error(ParsingErrorKind::InvalidToken, self.pos)),
+                None => return Err(self.error(ParsingErrorKind::InvalidToken, quote)),
+            }
+        }
+    }
+
+    // The length is of the string as written, before decoding its escapes
+    fn check_string(&self, start: usize, quote: usize) -> Result<(), ParsingError> {
+        self.counts
+            .string(self.pos - start)
+            .map_err(|kind| self.error(kind, quote))
+    }
+
+    // Parses the hex digits after `\u`, including the second half of a
+    // surrogate pair
+    fn parse_unicode_escape(&mut self) -> Option<char> {
+        let hex = |parser: &mut Self| {
+            let digits = parser.input.get(parser.pos..parser.pos + 4)?;
+            let n = u32::from_str_radix(digits, 16).ok()?;
+            // `from_str_radix` allows a leading `+`
+            if digits.starts_with('+') {
+                return None;
+            }
+            parser.pos += 4;
+            Some(n)
+        };
+        let high = hex(self)?;
+        match high {
+            0xD800..=0xDBFF => {
+                if !self.input[self.pos..].starts_with("\\u") {
+                    return None;
+                }
+                self.pos += 2;
+                let low = hex(self)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return None;
+                }
+                char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            }
+            _ => char::from_u32(high),
+        }
+    }
+}
+
+// Decodes the escapes of a string as stored in `JsonVal`. The parsers have
+// already checked them, anything else is kept as it is, and surrogates that
+// aren't part of a pair become U+FFFD.
+pub(crate) fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let hex = |s: &str| {
+        s.get(..4)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+    };
+    let mut s = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find('\\') {
+        s.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let mut chars = rest[1..].chars();
+        let (c, len) = match chars.next() {
+            Some('b') => ('\u{8}', 2),
+            Some('f') => ('\u{c}', 2),
+            Some('n') => ('\n', 2),
+            Some('r') => ('\r', 2),
+            Some('t') => ('\t', 2),
+            Some('u') => match hex(&rest[2..]) {
+                Some(high @ 0xD800..=0xDBFF) => {
+                    match rest.get(6..8).zip(rest.get(8..).and_then(hex)) {
+                        Some(("\\u", low @ 0xDC00..=0xDFFF)) => {
+                            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                            (char::from_u32(code).unwrap_or('\u{fffd}'), 12)
+                        }
+                        _ => ('\u{fffd}', 6),
+                    }
+                }
+                Some(code) => (char::from_u32(code).unwrap_or('\u{fffd}'), 6),
+                None => ('\\', 1),
+            },
+            Some(c) => (c, 1 + c.len_utf8()),
+            None => ('\\', 1),
+        };
+        s.push(c);
+        rest = &rest[len..];
+    }
+    s.push_str(rest);
+    Cow::Owned(s)
+}
+
+// Escapes a decoded string so it can be written between quotes
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
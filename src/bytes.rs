@@ -0,0 +1,561 @@
+use crate::number::{parse_number, Number};
+use crate::parser::{JsonVal, MapType};
+use crate::tokenizer::{Limits, Loc, ParsingError, ParsingErrorKind};
+
+// A parser working directly on bytes, for when throughput matters. It gives
+// the same values as `parser::parse_value`, but only accepts strict JSON.
+
+const WHITESPACE: u8 = 1 << 0;
+const DIGIT: u8 = 1 << 1;
+// Characters that can appear in a number after its first one
+const NUMBER: u8 = 1 << 2;
+// Characters that end a run of plain string bytes
+const STRING_STOP: u8 = 1 << 3;
+// Characters of literals like `true`
+const LITERAL: u8 = 1 << 4;
+
+static CLASSES: [u8; 256] = classes();
+
+const fn classes() -> [u8; 256] {
+    let mut classes = [0; 256];
+    let mut b = 0;
+    while b < 256 {
+        let c = b as u8;
+        let mut class = 0;
+        if matches!(c, b' ' | b'\t' | b'\n' | b'\r') {
+            class |= WHITESPACE;
+        }
+        if c.is_ascii_digit() {
+            class |= DIGIT | NUMBER;
+        }
+        if matches!(c, b'-' | b'+' | b'.' | b'e' | b'E') {
+            class |= NUMBER;
+        }
+        if c == b'"' || c == b'\\' || c < 0x20 {
+            class |= STRING_STOP;
+        }
+        if c.is_ascii_alphanumeric() {
+            class |= LITERAL;
+        }
+        classes[b] = class;
+        b += 1;
+    }
+    classes
+}
+
+fn is(b: u8, class: u8) -> bool {
+    CLASSES[b as usize] & class != 0
+}
+
+// Parses with the default `Limits`, so only the nesting is limited
+pub fn parse(input: &[u8]) -> Result<JsonVal, ParsingError> {
+    parse_with_limits(input, Limits::default())
+}
+
+// Like `parse`, checking every one of `limits` like the tokenizer does
+pub fn parse_with_limits(input: &[u8], limits: Limits) -> Result<JsonVal, ParsingError> {
+    check_input_size(input, limits)?;
+    let mut parser = Parser::new(to_str(input)?).with_limits(limits);
+    let val = parser.parse_val()?;
+    parser.finish()?;
+    Ok(val)
+}
+
+// Finds the end of the number starting at `start`, the bytes that can be part
+// of one. Integers that fit are accumulated while they are read, as the fast
+// path, and anything else is left for `parse_number` by returning `None`.
+pub(crate) fn scan_number(bytes: &[u8], start: usize) -> (usize, Option<Number>) {
+    let negative = bytes[start] == b'-';
+    let digits = start + negative as usize;
+    let mut pos = digits;
+    let mut n: u64 = 0;
+    let mut overflow = false;
+    while pos < bytes.len() && is(bytes[pos], DIGIT) {
+        let digit = (bytes[pos] - b'0') as u64;
+        match n.checked_mul(10).and_then(|n| n.checked_add(digit)) {
+            Some(next) => n = next,
+            None => overflow = true,
+        }
+        pos += 1;
+    }
+    let len = pos - digits;
+    let leading_zero = len > 1 && bytes[digits] == b'0';
+    if pos < bytes.len() && is(bytes[pos], NUMBER) {
+        while pos < bytes.len() && is(bytes[pos], NUMBER) {
+            pos += 1;
+        }
+        return (pos, None);
+    }
+    let num = match (len > 0 && !leading_zero && !overflow, negative) {
+        (false, _) => None,
+        (true, false) => Some(Number::UnsignedInt(n)),
+        (true, true) if n <= i64::MAX as u64 + 1 => {
+            Some(Number::SignedInt((n as i64).wrapping_neg()))
+        }
+        (true, true) => None,
+    };
+    (pos, num)
+}
+
+// As the whole input is there, its size is checked up front, failing where
+// it goes over
+pub(crate) fn check_input_size(input: &[u8], limits: Limits) -> Result<(), ParsingError> {
+    match limits.max_input_size {
+        Some(max) if input.len() > max => Err(ParsingError {
+            kind: ParsingErrorKind::InputTooLarge,
+            loc: loc_at(input, max),
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn to_str(input: &[u8]) -> Result<&str, ParsingError> {
+    core::str::from_utf8(input).map_err(|err| ParsingError {
+        kind: ParsingErrorKind::UnsupportedToken,
+        loc: loc_at(input, err.valid_up_to()),
+    })
+}
+
+// Finds the line and column of a byte offset, counting columns in characters
+// like the tokenizer does
+pub(crate) fn loc_at(input: &[u8], pos: usize) -> Loc {
+    let before = &input[..pos];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    Loc {
+        // Continuation bytes of multi-byte characters are not counted
+        col: before[line_start..]
+            .iter()
+            .filter(|b| **b & 0xC0 != 0x80)
+            .count()
+            + 1,
+        line: before.iter().filter(|b| **b == b'\n').count() + 1,
+    }
+}
+
+// What `Limits` limits, other than the input size, counted while parsing.
+// Shared by the parsers over bytes, which add the location to the error kind.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Counts {
+    limits: Limits,
+    depth: usize,
+    nodes: usize,
+}
+
+impl Counts {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            nodes: 0,
+        }
+    }
+
+    fn check(
+        len: usize,
+        limit: Option<usize>,
+        kind: ParsingErrorKind,
+    ) -> Result<(), ParsingErrorKind> {
+        match limit {
+            Some(limit) if len > limit => Err(kind),
+            _ => Ok(()),
+        }
+    }
+
+    // An array or object was opened
+    pub(crate) fn enter(&mut self) -> Result<(), ParsingErrorKind> {
+        self.depth += 1;
+        Self::check(
+            self.depth,
+            self.limits.max_depth,
+            ParsingErrorKind::DepthLimitExceeded,
+        )?;
+        self.node()
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Any value
+    pub(crate) fn node(&mut self) -> Result<(), ParsingErrorKind> {
+        self.nodes += 1;
+        Self::check(
+            self.nodes,
+            self.limits.max_nodes,
+            ParsingErrorKind::TooManyNodes,
+        )
+    }
+
+    // In bytes, before decoding escapes
+    pub(crate) fn string(&self, len: usize) -> Result<(), ParsingErrorKind> {
+        Self::check(
+            len,
+            self.limits.max_string_length,
+            ParsingErrorKind::StringTooLong,
+        )
+    }
+
+    pub(crate) fn number(&self, len: usize) -> Result<(), ParsingErrorKind> {
+        Self::check(
+            len,
+            self.limits.max_number_length,
+            ParsingErrorKind::NumberTooLong,
+        )
+    }
+
+    // `len` counts the element or member being added
+    pub(crate) fn len(&self, len: usize, object: bool) -> Result<(), ParsingErrorKind> {
+        if object {
+            Self::check(
+                len,
+                self.limits.max_members,
+                ParsingErrorKind::TooManyMembers,
+            )
+        } else {
+            Self::check(
+                len,
+                self.limits.max_elements,
+                ParsingErrorKind::TooManyElements,
+            )
+        }
+    }
+}
+
+// The tokens that values are built from, read byte by byte by `Parser` or taken
+// from the index of `simd`, so both build them with the same code
+pub(crate) trait Lexer<'a> {
+    // Skips to the start of the next value, returning its first byte
+    fn peek_val(&mut self) -> Option<u8>;
+
+    // Skips the opening bracket of an array or object, checking the depth
+    fn enter(&mut self) -> Result<(), ParsingError>;
+
+    fn leave(&mut self);
+
+    // Checks for the end of a container right after it was opened
+    fn is_empty(&mut self, end: u8) -> bool;
+
+    // Counts the element or member at the next value, `len` including it
+    fn check_len(&mut self, len: usize, close: u8) -> Result<(), ParsingError>;
+
+    // Parses a key and the colon after it
+    fn parse_ident(&mut self) -> Result<&'a str, ParsingError>;
+
+    // Parses a string value, returning its contents with the escapes left as
+    // they are, same as the tokenizer
+    fn parse_string_val(&mut self) -> Result<&'a str, ParsingError>;
+
+    // Parses a number or a literal like `true`
+    fn parse_scalar(&mut self) -> Result<JsonVal, ParsingError>;
+
+    // Skips the comma after an element, returning whether the container ended
+    fn parse_separator(&mut self, end: u8) -> Result<bool, ParsingError>;
+
+    // Checks that only whitespace is left after the top level value
+    fn finish(&mut self) -> Result<(), ParsingError>;
+
+    fn parse_val(&mut self) -> Result<JsonVal, ParsingError> {
+        match self.peek_val() {
+            Some(b'{') => {
+                self.enter()?;
+                let val = self.parse_object()?;
+                self.leave();
+                Ok(val)
+            }
+            Some(b'[') => {
+                self.enter()?;
+                let val = self.parse_array()?;
+                self.leave();
+                Ok(val)
+            }
+            Some(b'"') => Ok(JsonVal::String(self.parse_string_val()?.to_string())),
+            _ => self.parse_scalar(),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonVal, ParsingError> {
+        let mut map = MapType::new();
+        if self.is_empty(b'}') {
+            return Ok(JsonVal::Object(map));
+        }
+        // Duplicate keys count too, like in the tokenizer
+        let mut len = 0;
+        loop {
+            len += 1;
+            self.check_len(len, b'}')?;
+            let ident = self.parse_ident()?.to_string();
+            map.insert(ident, self.parse_val()?);
+            if self.parse_separator(b'}')? {
+                break Ok(JsonVal::Object(map));
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonVal, ParsingError> {
+        let mut arr = Vec::new();
+        if self.is_empty(b']') {
+            return Ok(JsonVal::Array(arr));
+        }
+        loop {
+            self.check_len(arr.len() + 1, b']')?;
+            arr.push(self.parse_val()?);
+            if self.parse_separator(b']')? {
+                break Ok(JsonVal::Array(arr));
+            }
+        }
+    }
+}
+
+// Also used by other parsers over bytes, like `tape`, that only need to build
+// something different from `JsonVal`
+pub(crate) struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    counts: Counts,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+            counts: Counts::new(Limits::default()),
+        }
+    }
+
+    // The input size isn't checked, see `check_input_size`
+    pub(crate) fn with_limits(mut self, limits: Limits) -> Self {
+        self.counts = Counts::new(limits);
+        self
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn nodes(&self) -> usize {
+        self.counts.nodes
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    pub(crate) fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    pub(crate) fn error(&self, kind: ParsingErrorKind, pos: usize) -> ParsingError {
+        ParsingError {
+            kind,
+            loc: loc_at(self.bytes, pos),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && is(self.bytes[self.pos], WHITESPACE) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_class(&mut self, class: u8) {
+        while self.pos < self.bytes.len() && is(self.bytes[self.pos], class) {
+            self.pos += 1;
+        }
+    }
+
+    // Checks that the next value is valid without building it
+    pub(crate) fn skip_val(&mut self) -> Result<(), ParsingError> {
+        match self.peek_val() {
+            Some(open @ (b'{' | b'[')) => {
+                self.enter()?;
+                let close = if open == b'{' { b'}' } else { b']' };
+                if !self.is_empty(close) {
+                    let mut len = 0;
+                    loop {
+                        len += 1;
+                        self.check_len(len, close)?;
+                        if open == b'{' {
+                            self.parse_ident()?;
+                        }
+                        self.skip_val()?;
+                        if self.parse_separator(close)? {
+                            break;
+                        }
+                    }
+                }
+                self.leave();
+                Ok(())
+            }
+            Some(b'"') => self.parse_string_val().map(|_| ()),
+            _ => self.parse_scalar().map(|_| ()),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Number, ParsingError> {
+        let start = self.pos;
+        let (end, num) = scan_number(self.bytes, start);
+        self.pos = end;
+        self.counts
+            .number(end - start)
+            .map_err(|kind| self.error(kind, start))?;
+        num.or_else(|| parse_number(&self.input[start..end]))
+            .ok_or_else(|| self.error(ParsingErrorKind::InvalidToken, start))
+    }
+
+    // Parses a string starting at its opening quote, returning its contents
+    // with the escapes left as they are, same as the tokenizer
+    pub(crate) fn parse_string(&mut self) -> Result<&'a str, ParsingError> {
+        use ParsingErrorKind::*;
+        let quote = self.pos;
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            self.pos += self.bytes[self.pos..]
+                .iter()
+                .position(|b| is(*b, STRING_STOP))
+                .unwrap_or(self.bytes.len() - self.pos);
+            match self.peek() {
+                Some(b'"') => {
+                    self.counts
+                        .string(self.pos - start)
+                        .map_err(|kind| self.error(kind, quote))?;
+                    self.pos += 1;
+                    break Ok(&self.input[start..self.pos - 1]);
+                }
+                Some(b'\\') => {
+                    let escape = self.pos;
+                    match self.bytes.get(escape + 1) {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                            self.pos += 2;
+                        }
+                        Some(b'u')
+                            if self
+                                .bytes
+                                .get(escape + 2..escape + 6)
+                                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) =>
+                        {
+                            self.pos += 6;
+                        }
+                        _ => break Err(self.error(InvalidToken, escape)),
+                    }
+                }
+                Some(_) => break Err(self.error(InvalidToken, self.pos)),
+                None => break Err(self.error(InvalidToken, quote)),
+            }
+        }
+    }
+}
+
+impl<'a> Lexer<'a> for Parser<'a> {
+    fn peek_val(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.peek()
+    }
+
+    fn enter(&mut self) -> Result<(), ParsingError> {
+        self.counts
+            .enter()
+            .map_err(|kind| self.error(kind, self.pos))?;
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.counts.leave();
+    }
+
+    fn is_empty(&mut self, end: u8) -> bool {
+        let empty = self.peek_val() == Some(end);
+        if empty {
+            self.pos += 1;
+        }
+        empty
+    }
+
+    fn check_len(&mut self, len: usize, close: u8) -> Result<(), ParsingError> {
+        self.peek_val();
+        self.counts
+            .len(len, close == b'}')
+            .map_err(|kind| self.error(kind, self.pos))
+    }
+
+    fn parse_ident(&mut self) -> Result<&'a str, ParsingError> {
+        if self.peek_val() != Some(b'"') {
+            return Err(self.error(ParsingErrorKind::UnexpectedToken, self.pos));
+        }
+        let ident = self.parse_string()?;
+        if self.peek_val() != Some(b':') {
+            return Err(self.error(ParsingErrorKind::UnexpectedToken, self.pos));
+        }
+        self.pos += 1;
+        Ok(ident)
+    }
+
+    fn parse_string_val(&mut self) -> Result<&'a str, ParsingError> {
+        self.counts
+            .node()
+            .map_err(|kind| self.error(kind, self.pos))?;
+        self.parse_string()
+    }
+
+    fn parse_scalar(&mut self) -> Result<JsonVal, ParsingError> {
+        use ParsingErrorKind::*;
+        let start = self.pos;
+        self.counts.node().map_err(|kind| self.error(kind, start))?;
+        match self.peek() {
+            Some(b) if b == b'-' || is(b, DIGIT) => self.parse_number().map(JsonVal::Number),
+            Some(b) if is(b, LITERAL) => {
+                self.skip_class(LITERAL);
+                match &self.bytes[start..self.pos] {
+                    b"true" => Ok(JsonVal::Boolean(true)),
+                    b"false" => Ok(JsonVal::Boolean(false)),
+                    b"null" => Ok(JsonVal::Null),
+                    _ => Err(self.error(InvalidToken, start)),
+                }
+            }
+            Some(b) if !b.is_ascii() => Err(self.error(UnsupportedToken, start)),
+            _ => Err(self.error(InvalidToken, start)),
+        }
+    }
+
+    fn parse_separator(&mut self, end: u8) -> Result<bool, ParsingError> {
+        use ParsingErrorKind::*;
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b',') => {
+                let comma = self.pos;
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(end) {
+                    Err(self.error(InvalidTrailingComma, comma))
+                } else {
+                    Ok(false)
+                }
+            }
+            Some(b) if b == end => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.error(MissingEndingComma, self.pos)),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), ParsingError> {
+        self.skip_whitespace();
+        if self.pos < self.bytes.len() {
+            return Err(self.error(ParsingErrorKind::UnexpectedToken, self.pos));
+        }
+        Ok(())
+    }
+}
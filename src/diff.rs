@@ -0,0 +1,173 @@
+use crate::parser::JsonVal;
+use crate::patch::{Patch, PatchOp};
+use crate::pointer::escape_token;
+
+// Computes a JSON Patch that turns `from` into `to`. Objects are diffed key
+// by key, following the order of `from` for removals and changes and the
+// order of `to` for additions. Reordering the keys of an object doesn't
+// produce any operations. Arrays are diffed using their longest common
+// subsequence, changed elements that line up are diffed recursively. The LCS
+// takes quadratic memory, so past `MAX_LCS_CELLS` arrays are diffed by position
+// instead, which can give a longer patch.
+pub fn diff(from: &JsonVal, to: &JsonVal) -> Patch {
+    let mut ops = Vec::new();
+    diff_impl(from, to, "", &mut ops);
+    Patch(ops)
+}
+
+fn diff_impl(from: &JsonVal, to: &JsonVal, path: &str, ops: &mut Vec<PatchOp>) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (JsonVal::Object(from), JsonVal::Object(to)) => {
+            for (key, from_val) in from {
+                let path = format!("{}/{}", path, escape_token(key));
+                match to.get(key) {
+                    Some(to_val) => diff_impl(from_val, to_val, &path, ops),
+                    None => ops.push(PatchOp::Remove { path }),
+                }
+            }
+            for (key, to_val) in to {
+                if !from.contains_key(key) {
+                    ops.push(PatchOp::Add {
+                        path: format!("{}/{}", path, escape_token(key)),
+                        value: to_val.clone(),
+                    });
+                }
+            }
+        }
+        (JsonVal::Array(from), JsonVal::Array(to)) => diff_array(from, to, path, ops),
+        _ => ops.push(PatchOp::Replace {
+            path: path.to_string(),
+            value: to.clone(),
+        }),
+    }
+}
+
+// About 32 MB of table, reached by two arrays of 2048 changed elements
+const MAX_LCS_CELLS: usize = 1 << 22;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+fn diff_array(from: &[JsonVal], to: &[JsonVal], path: &str, ops: &mut Vec<PatchOp>) {
+    // The common prefix and suffix don't need to go through the LCS
+    let prefix = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let from_mid = &from[prefix..from.len() - suffix];
+    let to_mid = &to[prefix..to.len() - suffix];
+
+    let (n, m) = (from_mid.len(), to_mid.len());
+    let edits = if (n + 1).saturating_mul(m + 1) > MAX_LCS_CELLS {
+        // Deleting everything and inserting it back pairs the elements up by
+        // position below
+        let mut edits = vec![Edit::Delete; n];
+        edits.resize(n + m, Edit::Insert);
+        edits
+    } else {
+        lcs_edits(from_mid, to_mid)
+    };
+    emit_edits(&edits, from_mid, to_mid, prefix, path, ops);
+}
+
+fn lcs_edits(from_mid: &[JsonVal], to_mid: &[JsonVal]) -> Vec<Edit> {
+    // lcs[i][j] is the length of the LCS of from_mid[i..] and to_mid[j..]
+    let (n, m) = (from_mid.len(), to_mid.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if from_mid[i] == to_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut edits = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && from_mid[i] == to_mid[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Delete);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert);
+            j += 1;
+        }
+    }
+    edits
+}
+
+fn emit_edits(
+    edits: &[Edit],
+    from_mid: &[JsonVal],
+    to_mid: &[JsonVal],
+    prefix: usize,
+    path: &str,
+    ops: &mut Vec<PatchOp>,
+) {
+    // Index into the array as it is being patched
+    let mut idx = prefix;
+    let (mut i, mut j) = (0, 0);
+    let mut e = 0;
+    while e < edits.len() {
+        match edits[e] {
+            Edit::Keep => {
+                idx += 1;
+                i += 1;
+                j += 1;
+                e += 1;
+            }
+            _ => {
+                // Pair up a run of deletions with the insertions that follow it,
+                // so changed elements become replacements
+                let deletes = edits[e..]
+                    .iter()
+                    .take_while(|&&e| e == Edit::Delete)
+                    .count();
+                let inserts = edits[e + deletes..]
+                    .iter()
+                    .take_while(|&&e| e == Edit::Insert)
+                    .count();
+                let paired = deletes.min(inserts);
+                for k in 0..paired {
+                    diff_impl(
+                        &from_mid[i + k],
+                        &to_mid[j + k],
+                        &format!("{}/{}", path, idx),
+                        ops,
+                    );
+                    idx += 1;
+                }
+                for _ in paired..deletes {
+                    ops.push(PatchOp::Remove {
+                        path: format!("{}/{}", path, idx),
+                    });
+                }
+                for k in paired..inserts {
+                    ops.push(PatchOp::Add {
+                        path: format!("{}/{}", path, idx),
+                        value: to_mid[j + k].clone(),
+                    });
+                    idx += 1;
+                }
+                i += deletes;
+                j += inserts;
+                e += deletes + inserts;
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::number::{is_valid_number, parse_f64, Number};
+use crate::parser::JsonVal;
+use core::fmt::Write;
+
+// Helpers for the JSON5 mode of the tokenizer, see `Options::json5`, and a
+// serializer for JSON5
+
+pub(crate) fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// Converts the contents of a JSON5 string to what they would be in a double
+// quoted JSON string, as that is how strings are stored. Returns `None` for
+// invalid escapes and unescaped control characters.
+pub(crate) fn normalize_string(text: &str, quote: char) -> Option<String> {
+    let mut normalized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let hex = |chars: &mut core::iter::Peekable<core::str::Chars>, len: usize| {
+        let digits: String = (0..len)
+            .map_while(|_| chars.next_if(char::is_ascii_hexdigit))
+            .collect();
+        (digits.len() == len).then_some(digits)
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // Line continuations are removed
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some('"') => normalized.push_str("\\\""),
+                Some(c @ ('\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {
+                    normalized.push('\\');
+                    normalized.push(c);
+                }
+                Some('u') => {
+                    normalized.push_str("\\u");
+                    normalized.push_str(&hex(&mut chars, 4)?);
+                }
+                Some('v') => normalized.push_str("\\u000b"),
+                // `\0` can't be followed by a digit, and other digits can't be
+                // escaped
+                Some('0') if chars.peek().is_none_or(|c| !c.is_ascii_digit()) => {
+                    normalized.push_str("\\u0000")
+                }
+                Some('0'..='9') => return None,
+                Some('x') => {
+                    normalized.push_str("\\u00");
+                    normalized.push_str(&hex(&mut chars, 2)?);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    normalized.push_str(&format!("\\u{:04x}", c as u32));
+                }
+                // Any other character is escaped as itself, including `'`
+                Some(c) => normalized.push(c),
+                None => return None,
+            },
+            '"' if quote == '\'' => normalized.push_str("\\\""),
+            '\u{0}'..='\u{1f}' => return None,
+            c => normalized.push(c),
+        }
+    }
+    Some(normalized)
+}
+
+// Parses the numbers JSON5 adds on top of JSON: hexadecimal, `Infinity`,
+// `NaN`, explicit `+` signs and leading or trailing decimal points. Returns
+// `None` for plain JSON numbers, which are parsed as usual.
+pub(crate) fn parse_number(text: &str) -> Option<Number> {
+    if is_valid_number(text) {
+        return None;
+    }
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    match unsigned {
+        "Infinity" => Some(Number::Float(sign * f64::INFINITY)),
+        "NaN" => Some(Number::Float(f64::NAN)),
+        _ if unsigned.starts_with("0x") || unsigned.starts_with("0X") => {
+            let n = u64::from_str_radix(&unsigned[2..], 16).ok()?;
+            if negative {
+                i64::try_from(-(n as i128)).ok().map(Number::SignedInt)
+            } else {
+                Some(Number::UnsignedInt(n))
+            }
+        }
+        _ => {
+            let mut normalized = String::from(if negative { "-" } else { "" });
+            if unsigned.starts_with('.') {
+                normalized.push('0');
+            }
+            normalized.push_str(unsigned);
+            let normalized = normalized.replace(".e", ".0e").replace(".E", ".0E");
+            let normalized = match normalized.strip_suffix('.') {
+                Some(s) => format!("{}.0", s),
+                None => normalized,
+            };
+            if !is_valid_number(&normalized) {
+                None
+            } else if normalized.contains(['.', 'e', 'E']) {
+                parse_f64(&normalized).map(Number::Float)
+            } else if negative {
+                normalized.parse().ok().map(Number::SignedInt)
+            } else {
+                normalized.parse().ok().map(Number::UnsignedInt)
+            }
+        }
+    }
+}
+
+// Same as `Display` for `JsonVal`, but keys that are valid identifiers are left
+// unquoted and non-finite floats are written as `Infinity` and `NaN`
+pub fn to_string(val: &JsonVal) -> String {
+    let mut s = String::new();
+    write_val(&mut s, val, 0);
+    s
+}
+
+fn write_indent(s: &mut String, depth: u8) {
+    for _ in 0..depth {
+        s.push_str("    ");
+    }
+}
+
+fn write_val(s: &mut String, val: &JsonVal, depth: u8) {
+    match val {
+        JsonVal::Array(arr) => {
+            s.push('[');
+            if !arr.is_empty() {
+                s.push('\n');
+                for (i, val) in arr.iter().enumerate() {
+                    write_indent(s, depth + 1);
+                    write_val(s, val, depth + 1);
+                    if i != arr.len() - 1 {
+                        s.push(',');
+                    }
+                    s.push('\n');
+                }
+                write_indent(s, depth);
+            }
+            s.push(']');
+        }
+        JsonVal::Object(obj) => {
+            s.push('{');
+            if !obj.is_empty() {
+                s.push('\n');
+                for (i, (ident, val)) in obj.iter().enumerate() {
+                    write_indent(s, depth + 1);
+                    let mut chars = ident.chars();
+                    if chars.next().is_some_and(is_ident_start) && chars.all(is_ident_char) {
+                        s.push_str(ident);
+                    } else {
+                        let _ = write!(s, "\"{}\"", ident);
+                    }
+                    s.push_str(": ");
+                    write_val(s, val, depth + 1);
+                    if i != obj.len() - 1 {
+                        s.push(',');
+                    }
+                    s.push('\n');
+                }
+                write_indent(s, depth);
+            }
+            s.push('}');
+        }
+        JsonVal::Number(Number::Float(n)) if n.is_nan() => s.push_str("NaN"),
+        JsonVal::Number(Number::Float(n)) if n.is_infinite() => {
+            s.push_str(if *n > 0.0 { "Infinity" } else { "-Infinity" });
+        }
+        val => {
+            let _ = write!(s, "{}", val);
+        }
+    }
+}
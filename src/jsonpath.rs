@@ -0,0 +1,820 @@
+use crate::borrowed::{escape, unescape};
+use crate::number::{is_valid_number, parse_f64, Number};
+use crate::parser::JsonVal;
+use core::cmp::Ordering;
+use core::fmt::Display;
+use core::iter::Peekable;
+use core::str::CharIndices;
+
+// JSONPath queries (RFC 9535), like `$.store.book[?(@.price < 10)].title`.
+// Function extensions (`length()`, `match()`, ...) are not supported. Names
+// and strings are compared after decoding their escapes, so `$['a"b']` matches
+// the key written `"a\"b"`.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PathErrorKind {
+    MissingRoot,
+    UnexpectedChar,
+    UnexpectedEnd,
+    InvalidName,
+    InvalidNumber,
+    InvalidEscape,
+    // Comparisons can only use queries that select at most one node
+    NonSingularQuery,
+    // A literal can't be used on its own as a filter
+    InvalidFilter,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PathError {
+    pub(crate) kind: PathErrorKind,
+    // Byte offset into the query
+    pub(crate) pos: usize,
+}
+
+impl PathError {
+    pub fn kind(&self) -> PathErrorKind {
+        self.kind
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} at offset {}", self.kind, self.pos)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+type Result<T> = std::result::Result<T, PathError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+// A node selected by a query, and its location as a normalized path, like
+// `$['store']['book'][0]`
+#[derive(Debug, PartialEq)]
+pub struct Node<'a> {
+    pub path: String,
+    pub val: &'a JsonVal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Not(Box<Filter>),
+    Exists(Query),
+    Compare(Comparable, CmpOp, Comparable),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Comparable {
+    Literal(JsonVal),
+    Query(Query),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    // Starts at `@` instead of `$`
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => {
+                matches!(selectors[..], [Selector::Name(_) | Selector::Index(_)])
+            }
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+// Largest integer that can be exactly represented as an `f64`, the limit for
+// indices in RFC 9535
+const MAX_INT: i64 = (1 << 53) - 1;
+
+struct Compiler<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Compiler<'a> {
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.text.len(), |(i, _)| *i)
+    }
+
+    fn error<T>(&mut self, kind: PathErrorKind) -> Result<T> {
+        Err(PathError {
+            kind,
+            pos: self.pos(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn next_if_eq(&mut self, c: char) -> bool {
+        self.chars.next_if(|(_, next)| *next == c).is_some()
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(_) => self.error(PathErrorKind::UnexpectedChar),
+            None => self.error(PathErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    fn segments(&mut self, in_filter: bool) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            if in_filter {
+                // Whitespace is allowed between segments, but it could also be the
+                // end of the query, so only skip it if a segment follows
+                let mut lookahead = self.chars.clone();
+                while lookahead
+                    .next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r'))
+                    .is_some()
+                {}
+                if let Some((_, '.' | '[')) = lookahead.peek() {
+                    self.skip_whitespace();
+                }
+            }
+            match self.peek() {
+                Some('[') => segments.push(Segment::Child(self.bracketed()?)),
+                Some('.') => {
+                    self.chars.next();
+                    if self.next_if_eq('.') {
+                        let selectors = match self.peek() {
+                            Some('[') => self.bracketed()?,
+                            Some('*') => {
+                                self.chars.next();
+                                vec![Selector::Wildcard]
+                            }
+                            _ => vec![Selector::Name(self.member_name()?)],
+                        };
+                        segments.push(Segment::Descendant(selectors));
+                    } else if self.next_if_eq('*') {
+                        segments.push(Segment::Child(vec![Selector::Wildcard]));
+                    } else {
+                        segments.push(Segment::Child(vec![Selector::Name(self.member_name()?)]));
+                    }
+                }
+                _ => break Ok(segments),
+            }
+        }
+    }
+
+    fn member_name(&mut self) -> Result<String> {
+        let is_first = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+        match self.peek() {
+            Some(c) if is_first(c) => {}
+            Some(_) => return self.error(PathErrorKind::InvalidName),
+            None => return self.error(PathErrorKind::UnexpectedEnd),
+        }
+        let mut name = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| is_first(*c) || c.is_ascii_digit())
+        {
+            name.push(c);
+        }
+        Ok(name)
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if !self.next_if_eq(',') {
+                break;
+            }
+        }
+        self.expect(']')?;
+        Ok(selectors)
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.chars.next();
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.chars.next();
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.logical_or()?))
+            }
+            Some('-' | '0'..='9' | ':') => {
+                let start = self.maybe_int()?;
+                self.skip_whitespace();
+                if !self.next_if_eq(':') {
+                    return match start {
+                        Some(i) => Ok(Selector::Index(i)),
+                        None => self.error(PathErrorKind::InvalidNumber),
+                    };
+                }
+                self.skip_whitespace();
+                let end = self.maybe_int()?;
+                self.skip_whitespace();
+                let step = if self.next_if_eq(':') {
+                    self.skip_whitespace();
+                    self.maybe_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+            Some(_) => self.error(PathErrorKind::UnexpectedChar),
+            None => self.error(PathErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn maybe_int(&mut self) -> Result<Option<i64>> {
+        if !matches!(self.peek(), Some('-' | '0'..='9')) {
+            return Ok(None);
+        }
+        let pos = self.pos();
+        let mut text = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| c.is_ascii_digit() || (*c == '-' && text.is_empty()))
+        {
+            text.push(c);
+        }
+        let digits = text.trim_start_matches('-');
+        let valid =
+            !digits.is_empty() && (digits == "0" || !digits.starts_with('0')) && text != "-0";
+        match text.parse::<i64>() {
+            Ok(i) if valid && (-MAX_INT..=MAX_INT).contains(&i) => Ok(Some(i)),
+            _ => Err(PathError {
+                kind: PathErrorKind::InvalidNumber,
+                pos,
+            }),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let Some((_, quote)) = self.chars.next() else {
+            return self.error(PathErrorKind::UnexpectedEnd);
+        };
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, c)) if c == quote => break Ok(s),
+                Some((_, '\\')) => s.push(self.escape(quote)?),
+                Some((pos, c)) if (c as u32) < 0x20 => {
+                    break Err(PathError {
+                        kind: PathErrorKind::UnexpectedChar,
+                        pos,
+                    })
+                }
+                Some((_, c)) => s.push(c),
+                None => break self.error(PathErrorKind::UnexpectedEnd),
+            }
+        }
+    }
+
+    fn escape(&mut self, quote: char) -> Result<char> {
+        let pos = self.pos();
+        let invalid = || PathError {
+            kind: PathErrorKind::InvalidEscape,
+            pos,
+        };
+        match self.chars.next().map(|(_, c)| c) {
+            Some(c) if c == quote => Ok(c),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('/') => Ok('/'),
+            Some('\\') => Ok('\\'),
+            Some('u') => {
+                let high = self.hex4().ok_or_else(invalid)?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if !(self.next_if_eq('\\') && self.next_if_eq('u')) {
+                        return Err(invalid());
+                    }
+                    match self.hex4() {
+                        Some(low) if (0xDC00..0xE000).contains(&low) => {
+                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                        }
+                        _ => return Err(invalid()),
+                    }
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(invalid)
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let (_, c) = self.chars.next_if(|(_, c)| c.is_ascii_hexdigit())?;
+            code = code * 16 + c.to_digit(16)?;
+        }
+        Some(code)
+    }
+
+    fn logical_or(&mut self) -> Result<Filter> {
+        let mut operands = vec![self.logical_and()?];
+        loop {
+            self.skip_whitespace();
+            if self.next_if_eq('|') {
+                self.expect('|')?;
+                self.skip_whitespace();
+                operands.push(self.logical_and()?);
+            } else {
+                break;
+            }
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Filter::Or(operands)
+        })
+    }
+
+    fn logical_and(&mut self) -> Result<Filter> {
+        let mut operands = vec![self.basic_expr()?];
+        loop {
+            self.skip_whitespace();
+            if self.next_if_eq('&') {
+                self.expect('&')?;
+                self.skip_whitespace();
+                operands.push(self.basic_expr()?);
+            } else {
+                break;
+            }
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Filter::And(operands)
+        })
+    }
+
+    fn basic_expr(&mut self) -> Result<Filter> {
+        if self.next_if_eq('!') {
+            self.skip_whitespace();
+            return Ok(Filter::Not(Box::new(self.basic_expr()?)));
+        }
+        if self.next_if_eq('(') {
+            self.skip_whitespace();
+            let filter = self.logical_or()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(filter);
+        }
+
+        let pos = self.pos();
+        let left = self.comparable()?;
+        self.skip_whitespace();
+        let op = match self.peek() {
+            Some('=') => {
+                self.chars.next();
+                self.expect('=')?;
+                CmpOp::Eq
+            }
+            Some('!') => {
+                self.chars.next();
+                self.expect('=')?;
+                CmpOp::Ne
+            }
+            Some('<') => {
+                self.chars.next();
+                if self.next_if_eq('=') {
+                    CmpOp::Le
+                } else {
+                    CmpOp::Lt
+                }
+            }
+            Some('>') => {
+                self.chars.next();
+                if self.next_if_eq('=') {
+                    CmpOp::Ge
+                } else {
+                    CmpOp::Gt
+                }
+            }
+            // Not a comparison, so it must be an existence test
+            _ => {
+                return match left {
+                    Comparable::Query(query) => Ok(Filter::Exists(query)),
+                    Comparable::Literal(_) => Err(PathError {
+                        kind: PathErrorKind::InvalidFilter,
+                        pos,
+                    }),
+                };
+            }
+        };
+        if let Comparable::Query(ref query) = left {
+            if !query.is_singular() {
+                return Err(PathError {
+                    kind: PathErrorKind::NonSingularQuery,
+                    pos,
+                });
+            }
+        }
+        self.skip_whitespace();
+        let pos = self.pos();
+        let right = self.comparable()?;
+        if let Comparable::Query(ref query) = right {
+            if !query.is_singular() {
+                return Err(PathError {
+                    kind: PathErrorKind::NonSingularQuery,
+                    pos,
+                });
+            }
+        }
+        Ok(Filter::Compare(left, op, right))
+    }
+
+    fn comparable(&mut self) -> Result<Comparable> {
+        match self.peek() {
+            Some('@') => {
+                self.chars.next();
+                Ok(Comparable::Query(Query {
+                    relative: true,
+                    segments: self.segments(true)?,
+                }))
+            }
+            Some('$') => {
+                self.chars.next();
+                Ok(Comparable::Query(Query {
+                    relative: false,
+                    segments: self.segments(true)?,
+                }))
+            }
+            // Stored escaped like the strings of the document
+            Some('\'' | '"') => Ok(Comparable::Literal(JsonVal::String(escape(
+                &self.string()?,
+            )))),
+            Some('-' | '0'..='9') => Ok(Comparable::Literal(JsonVal::Number(self.number()?))),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let pos = self.pos();
+                let mut word = String::new();
+                while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric()) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Ok(Comparable::Literal(JsonVal::Boolean(true))),
+                    "false" => Ok(Comparable::Literal(JsonVal::Boolean(false))),
+                    "null" => Ok(Comparable::Literal(JsonVal::Null)),
+                    _ => Err(PathError {
+                        kind: PathErrorKind::UnexpectedChar,
+                        pos,
+                    }),
+                }
+            }
+            Some(_) => self.error(PathErrorKind::UnexpectedChar),
+            None => self.error(PathErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn number(&mut self) -> Result<Number> {
+        let pos = self.pos();
+        let mut text = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(c);
+        }
+        if !is_valid_number(&text) {
+            return Err(PathError {
+                kind: PathErrorKind::InvalidNumber,
+                pos,
+            });
+        }
+        let num = if text.contains(['.', 'e', 'E']) {
+            parse_f64(&text).map(Number::Float)
+        } else if text.starts_with('-') {
+            text.parse().ok().map(Number::SignedInt)
+        } else {
+            text.parse().ok().map(Number::UnsignedInt)
+        };
+        num.or_else(|| parse_f64(&text).map(Number::Float))
+            .ok_or(PathError {
+                kind: PathErrorKind::InvalidNumber,
+                pos,
+            })
+    }
+}
+
+impl JsonPath {
+    pub fn compile(text: &str) -> Result<JsonPath> {
+        let mut compiler = Compiler {
+            text,
+            chars: text.char_indices().peekable(),
+        };
+        if !compiler.next_if_eq('$') {
+            return compiler.error(PathErrorKind::MissingRoot);
+        }
+        let segments = compiler.segments(false)?;
+        if compiler.peek().is_some() {
+            return compiler.error(PathErrorKind::UnexpectedChar);
+        }
+        Ok(JsonPath { segments })
+    }
+
+    // Returns every selected node, in the order given by RFC 9535
+    pub fn query<'a>(&self, root: &'a JsonVal) -> Vec<Node<'a>> {
+        let nodes = select(&self.segments, root, vec![(vec![], root)]);
+        nodes
+            .into_iter()
+            .map(|(path, val)| Node {
+                path: normalized_path(&path),
+                val,
+            })
+            .collect()
+    }
+}
+
+impl JsonVal {
+    pub fn query(&self, path: &str) -> Result<Vec<Node<'_>>> {
+        Ok(JsonPath::compile(path)?.query(self))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PathElem<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+type Nodes<'a> = Vec<(Vec<PathElem<'a>>, &'a JsonVal)>;
+
+fn select<'a>(segments: &[Segment], root: &'a JsonVal, mut nodes: Nodes<'a>) -> Nodes<'a> {
+    for segment in segments {
+        let mut selected = Vec::new();
+        for (path, val) in nodes {
+            match segment {
+                Segment::Child(selectors) => {
+                    apply_selectors(selectors, root, &path, val, &mut selected);
+                }
+                Segment::Descendant(selectors) => {
+                    let mut descendants = vec![(path, val)];
+                    collect_descendants(&mut descendants, 0);
+                    for (path, val) in descendants {
+                        apply_selectors(selectors, root, &path, val, &mut selected);
+                    }
+                }
+            }
+        }
+        nodes = selected;
+    }
+    nodes
+}
+
+// Appends every descendant of `nodes[i]` after it, in document order
+fn collect_descendants<'a>(nodes: &mut Nodes<'a>, i: usize) {
+    let (path, val) = nodes[i].clone();
+    match val {
+        JsonVal::Array(arr) => {
+            for (j, child) in arr.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(PathElem::Index(j));
+                nodes.push((path, child));
+                collect_descendants(nodes, nodes.len() - 1);
+            }
+        }
+        JsonVal::Object(obj) => {
+            for (key, child) in obj {
+                let mut path = path.clone();
+                path.push(PathElem::Key(key));
+                nodes.push((path, child));
+                collect_descendants(nodes, nodes.len() - 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn apply_selectors<'a>(
+    selectors: &[Selector],
+    root: &'a JsonVal,
+    path: &[PathElem<'a>],
+    val: &'a JsonVal,
+    selected: &mut Nodes<'a>,
+) {
+    let mut push = |elem: PathElem<'a>, child: &'a JsonVal| {
+        let mut path = path.to_vec();
+        path.push(elem);
+        selected.push((path, child));
+    };
+    for selector in selectors {
+        match (selector, val) {
+            (Selector::Name(name), JsonVal::Object(obj)) => {
+                // Keys are stored with their escapes, which only need decoding
+                // when the name isn't found as is
+                let found = obj.get_key_value(name.as_str()).or_else(|| {
+                    obj.iter()
+                        .find(|(key, _)| key.contains('\\') && unescape(key) == name.as_str())
+                });
+                if let Some((key, child)) = found {
+                    push(PathElem::Key(key), child);
+                }
+            }
+            (Selector::Wildcard, JsonVal::Object(obj)) => {
+                for (key, child) in obj {
+                    push(PathElem::Key(key), child);
+                }
+            }
+            (Selector::Wildcard, JsonVal::Array(arr)) => {
+                for (i, child) in arr.iter().enumerate() {
+                    push(PathElem::Index(i), child);
+                }
+            }
+            (Selector::Index(i), JsonVal::Array(arr)) => {
+                let len = arr.len() as i64;
+                let i = if *i < 0 { len + i } else { *i };
+                if (0..len).contains(&i) {
+                    push(PathElem::Index(i as usize), &arr[i as usize]);
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonVal::Array(arr)) => {
+                for i in slice_indices(arr.len() as i64, *start, *end, step.unwrap_or(1)) {
+                    push(PathElem::Index(i), &arr[i]);
+                }
+            }
+            (Selector::Filter(filter), JsonVal::Array(arr)) => {
+                for (i, child) in arr.iter().enumerate() {
+                    if test_filter(filter, root, child) {
+                        push(PathElem::Index(i), child);
+                    }
+                }
+            }
+            (Selector::Filter(filter), JsonVal::Object(obj)) => {
+                for (key, child) in obj {
+                    if test_filter(filter, root, child) {
+                        push(PathElem::Key(key), child);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+fn test_filter(filter: &Filter, root: &JsonVal, current: &JsonVal) -> bool {
+    match filter {
+        Filter::Or(operands) => operands.iter().any(|f| test_filter(f, root, current)),
+        Filter::And(operands) => operands.iter().all(|f| test_filter(f, root, current)),
+        Filter::Not(filter) => !test_filter(filter, root, current),
+        Filter::Exists(query) => !eval_query(query, root, current).is_empty(),
+        Filter::Compare(left, op, right) => {
+            let left = eval_comparable(left, root, current);
+            let right = eval_comparable(right, root, current);
+            compare(left, *op, right)
+        }
+    }
+}
+
+fn eval_query<'a>(query: &Query, root: &'a JsonVal, current: &'a JsonVal) -> Nodes<'a> {
+    let start = if query.relative { current } else { root };
+    select(&query.segments, root, vec![(vec![], start)])
+}
+
+// `None` if the query selected nothing
+fn eval_comparable<'a>(
+    comparable: &'a Comparable,
+    root: &'a JsonVal,
+    current: &'a JsonVal,
+) -> Option<&'a JsonVal> {
+    match comparable {
+        Comparable::Literal(val) => Some(val),
+        Comparable::Query(query) => eval_query(query, root, current)
+            .into_iter()
+            .next()
+            .map(|(_, val)| val),
+    }
+}
+
+fn compare(left: Option<&JsonVal>, op: CmpOp, right: Option<&JsonVal>) -> bool {
+    let less = |a: Option<&JsonVal>, b: Option<&JsonVal>| match (a, b) {
+        (Some(JsonVal::Number(a)), Some(JsonVal::Number(b))) => a.cmp(b) == Ordering::Less,
+        (Some(JsonVal::String(a)), Some(JsonVal::String(b))) => unescape(a) < unescape(b),
+        _ => false,
+    };
+    let equal = match (left, right) {
+        (Some(JsonVal::String(a)), Some(JsonVal::String(b))) => unescape(a) == unescape(b),
+        _ => left == right,
+    };
+    match op {
+        CmpOp::Eq => equal,
+        CmpOp::Ne => !equal,
+        CmpOp::Lt => less(left, right),
+        CmpOp::Le => less(left, right) || equal,
+        CmpOp::Gt => less(right, left),
+        CmpOp::Ge => less(right, left) || equal,
+    }
+}
+
+fn normalized_path(path: &[PathElem]) -> String {
+    let mut s = String::from("$");
+    for elem in path {
+        match elem {
+            PathElem::Index(i) => s.push_str(&format!("[{}]", i)),
+            PathElem::Key(key) => {
+                s.push_str("['");
+                for c in unescape(key).chars() {
+                    match c {
+                        '\'' => s.push_str("\\'"),
+                        '\\' => s.push_str("\\\\"),
+                        '\u{8}' => s.push_str("\\b"),
+                        '\u{c}' => s.push_str("\\f"),
+                        '\n' => s.push_str("\\n"),
+                        '\r' => s.push_str("\\r"),
+                        '\t' => s.push_str("\\t"),
+                        c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => s.push(c),
+                    }
+                }
+                s.push_str("']");
+            }
+        }
+    }
+    s
+}
@@ -1,8 +1,38 @@
+#[cfg(feature = "async")]
+pub mod async_parser;
+pub mod borrowed;
+pub mod bytes;
+pub mod diff;
+pub mod json5;
+pub mod jsonpath;
+pub mod merge_patch;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod ndjson;
+pub mod number;
+pub mod ondemand;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod push;
+pub mod schema;
+#[cfg(feature = "simd")]
+pub mod simd;
+pub mod stream;
+pub mod tape;
 pub mod tokenizer;
 
+pub use borrowed::JsonValRef;
+#[cfg(feature = "mmap")]
+pub use mmap::from_file;
+pub use number::NonFinite;
+pub use number::NonFiniteError;
 pub use parser::JsonVal;
 pub use parser::Number;
+pub use tokenizer::Limits;
+pub use tokenizer::Options;
 
 #[cfg(test)]
 mod tests {
@@ -65,7 +95,7 @@ mod tests {
     fn wikipedia() {
         // Example taken from https://wikipedia.org/wiki/JSON
         let input = include_str!("../tests/wikipedia.json");
-        let tokenizer = tokenizer::Tokenizer::from_str(&input);
+        let tokenizer = tokenizer::Tokenizer::from_str(input);
         let parsed = parser::parse(tokenizer).expect("Expected valid json");
         if let JsonVal::Object(ref parsed) = parsed {
             assert_eq!(parsed["firstName"], json_str!("John"));
@@ -99,7 +129,7 @@ mod tests {
     fn jsonplaceholder() {
         // Example taken from https://jsonplaceholder.typicode.com/todos/?userId=1
         let input = include_str!("../tests/jsonplaceholder.json");
-        let tokenizer = tokenizer::Tokenizer::from_str(&input);
+        let tokenizer = tokenizer::Tokenizer::from_str(input);
         let parsed = parser::parse(tokenizer).expect("Expected valid json");
         if let JsonVal::Array(ref parsed) = parsed {
             for val in parsed {
@@ -124,7 +154,7 @@ mod tests {
     fn jsonorg() {
         // Example taken from https://www.json.org/example.html
         let input = include_str!("../tests/jsonorg.json");
-        let tokenizer = tokenizer::Tokenizer::from_str(&input);
+        let tokenizer = tokenizer::Tokenizer::from_str(input);
         let parsed = parser::parse(tokenizer).expect("Expected valid json");
         if let JsonVal::Object(parsed) = parsed {
             if let JsonVal::Object(ref widget) = parsed["widget"] {
@@ -168,6 +198,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn tsoding() {
         // Example taken from https://github.com/tsoding/haskell-json
         let input = r#"{
@@ -175,7 +206,7 @@ mod tests {
                 "world": null
                 }
             "#;
-        let tokenizer = tokenizer::Tokenizer::from_str(&input);
+        let tokenizer = tokenizer::Tokenizer::from_str(input);
         let parsed = parser::parse(tokenizer).expect("Expected valid json");
         if let JsonVal::Object(ref parsed) = parsed {
             if let JsonVal::Array(ref hello) = parsed["hello"] {
@@ -193,7 +224,6 @@ mod tests {
                     assert_eq!(arr[3], json_num!(0.000004; float));
                     assert_eq!(arr[4], json_num!(5000000f64; float));
                     assert_eq!(arr[5], json_num!(1.23; float));
-                } else {
                 }
             } else {
                 unreachable!("Must parse as an array, {:#?}", parsed)
@@ -202,4 +232,1928 @@ mod tests {
             unreachable!("Must parse as an object, {:#?}", parsed)
         }
     }
+
+    #[test]
+    fn literals() {
+        use tokenizer::ParsingErrorKind;
+
+        let input = "[true, -1, null]";
+        let parsed =
+            parser::parse(tokenizer::Tokenizer::from_str(input)).expect("Expected valid json");
+        assert_eq!(
+            parsed,
+            json_arr!(vec![
+                JsonVal::Boolean(true),
+                json_num!(-1; int),
+                JsonVal::Null
+            ])
+        );
+
+        // Whitespace ends a literal instead of being skipped inside of it, so
+        // these aren't read as `true`, `-12` and `null`
+        for input in ["[tr ue]", "[-1 2]", "[nu\nll]"] {
+            let err = parser::parse(tokenizer::Tokenizer::from_str(input))
+                .expect_err("Expected an error");
+            assert!(matches!(err.kind, ParsingErrorKind::MissingEndingComma));
+        }
+
+        // Any value is accepted at the top level, but nothing after it
+        let parsed = parser::parse(tokenizer::Tokenizer::from_str(" -1 ")).unwrap();
+        assert_eq!(parsed, json_num!(-1; int));
+        for (input, col) in [("[1]]", 4), ("[][]", 3), ("{} x", 4)] {
+            let err = parser::parse(tokenizer::Tokenizer::from_str(input))
+                .expect_err("Expected an error");
+            assert_eq!((err.loc.line, err.loc.col), (1, col), "{input}");
+        }
+    }
+
+    #[test]
+    fn arbitrary_precision() {
+        let input = r#"[340282366920938463463374607431768211455, -170141183460469231731687303715884105728, 0.1, 1e400]"#;
+        let tokenizer = tokenizer::Tokenizer::from_str(input).with_options(Options {
+            arbitrary_precision: true,
+            ..Options::default()
+        });
+        let parsed = parser::parse(tokenizer).expect("Expected valid json");
+        if let JsonVal::Array(ref arr) = parsed {
+            assert_eq!(arr[2], JsonVal::Number(Number::Raw("0.1".to_string())));
+            if let (JsonVal::Number(a), JsonVal::Number(b), JsonVal::Number(c)) =
+                (&arr[0], &arr[1], &arr[2])
+            {
+                assert_eq!(a.as_u128(), Some(u128::MAX));
+                assert_eq!(a.as_i128(), None);
+                assert_eq!(b.as_i128(), Some(i128::MIN));
+                assert_eq!(c.as_f64(), 0.1);
+                assert_eq!(c.as_u128(), None);
+                #[cfg(feature = "bigdecimal")]
+                assert_eq!(c.as_big_decimal(), "0.1".parse().ok());
+            } else {
+                unreachable!("Must parse as numbers, {:#?}", parsed)
+            }
+        } else {
+            unreachable!("Must parse as an array, {:#?}", parsed)
+        }
+        assert_eq!(
+            parsed.to_string(),
+            "[\n    340282366920938463463374607431768211455,\n    -170141183460469231731687303715884105728,\n    0.1,\n    1e400\n]"
+        );
+
+        let tokenizer = tokenizer::Tokenizer::from_str("[01]").with_options(Options {
+            arbitrary_precision: true,
+            ..Options::default()
+        });
+        assert!(parser::parse(tokenizer).is_err());
+    }
+
+    #[test]
+    fn float_round_trip() {
+        let floats = [
+            0.1,
+            -0.0,
+            5e6,
+            1e300,
+            1e-7,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            0.30000000000000004,
+            123456.789,
+        ];
+        let arr = json_arr!(floats.iter().map(|&n| json_num!(n; float)).collect());
+        let text = arr.to_string();
+        let parsed =
+            parser::parse(tokenizer::Tokenizer::from_str(&text)).expect("Expected valid json");
+        if let JsonVal::Array(ref parsed) = parsed {
+            for (val, n) in parsed.iter().zip(floats) {
+                if let JsonVal::Number(Number::Float(parsed)) = val {
+                    assert_eq!(parsed.to_bits(), n.to_bits());
+                } else {
+                    unreachable!("Must parse as a float, {:#?}", val)
+                }
+            }
+        } else {
+            unreachable!("Must parse as an array, {:#?}", parsed)
+        }
+        assert_eq!(json_num!(5e6; float).to_string(), "5000000.0");
+        assert_eq!(json_num!(1e300; float).to_string(), "1e300");
+        assert_eq!(json_num!(0.1; float).to_string(), "0.1");
+
+        let non_finite = json_arr!(vec![
+            json_num!(f64::NAN; float),
+            json_num!(f64::INFINITY; float)
+        ]);
+        assert_eq!(
+            non_finite.to_string_with(NonFinite::Null),
+            Ok("[\n    null,\n    null\n]".to_string())
+        );
+        let err = non_finite.to_string_with(NonFinite::Error).unwrap_err();
+        assert!(err.value().is_nan());
+        assert_eq!(err.to_string(), "NaN can't be written as JSON");
+
+        // Too big for an `f64`, parsed as infinity like before and written
+        // according to the policy
+        let tokenizer = tokenizer::Tokenizer::from_str("[1e400, -1e400]");
+        let big = parser::parse(tokenizer).unwrap();
+        assert_eq!(
+            big,
+            json_arr!(vec![
+                json_num!(f64::INFINITY; float),
+                json_num!(f64::NEG_INFINITY; float)
+            ])
+        );
+        assert_eq!(big.to_compact_string(), "[null,null]");
+        let err = big.to_string_with(NonFinite::Error).unwrap_err();
+        assert_eq!(err.value(), f64::INFINITY);
+        assert_eq!(
+            bytes::parse(b"1e400").unwrap(),
+            json_num!(f64::INFINITY; float)
+        );
+    }
+
+    #[test]
+    fn number_equality() {
+        use std::collections::HashSet;
+
+        let raw = |s: &str| Number::Raw(s.to_string());
+        assert_eq!(Number::UnsignedInt(1), Number::SignedInt(1));
+        assert_eq!(Number::SignedInt(1), Number::Float(1.0));
+        assert_eq!(Number::Float(1.0), raw("1e0"));
+        assert_eq!(Number::Float(0.1), raw("0.100"));
+        assert_eq!(Number::Float(0.0), Number::Float(-0.0));
+        assert_eq!(Number::Float(f64::NAN), Number::Float(f64::NAN));
+        assert_ne!(
+            raw("340282366920938463463374607431768211455"),
+            raw("3.4e38")
+        );
+
+        let mut sorted = vec![
+            Number::Float(f64::NAN),
+            raw("1e400"),
+            Number::UnsignedInt(u64::MAX),
+            Number::Float(0.5),
+            Number::SignedInt(-3),
+            Number::Float(f64::NEG_INFINITY),
+            raw("-0.00001"),
+            Number::UnsignedInt(0),
+        ];
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            vec![
+                Number::Float(f64::NEG_INFINITY),
+                Number::SignedInt(-3),
+                raw("-0.00001"),
+                Number::UnsignedInt(0),
+                Number::Float(0.5),
+                Number::UnsignedInt(u64::MAX),
+                raw("1e400"),
+                Number::Float(f64::NAN),
+            ]
+        );
+
+        let set: HashSet<_> = [
+            json_num!(2; uint),
+            json_num!(2; int),
+            json_num!(2.0; float),
+            JsonVal::Number(raw("20e-1")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn value_hashing() {
+        use std::collections::HashSet;
+
+        let a = json_obj!({"a", json_num!(1; uint)}, {"b", json_arr!(vec![JsonVal::Null])});
+        let b = json_obj!({"b", json_arr!(vec![JsonVal::Null])}, {"a", json_num!(1.0; float)});
+        let c = json_obj!({"a", json_num!(2; uint)}, {"b", json_arr!(vec![JsonVal::Null])});
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), core::cmp::Ordering::Equal);
+        assert!(a < c);
+        assert!(JsonVal::Null < JsonVal::Boolean(false));
+
+        let set: HashSet<_> = [a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn json_pointer() {
+        use pointer::PointerErrorKind;
+
+        let input = include_str!("../tests/jsonorg.json");
+        let mut parsed =
+            parser::parse(tokenizer::Tokenizer::from_str(input)).expect("Expected valid json");
+        assert_eq!(
+            parsed.pointer("/widget/window/title"),
+            Ok(&json_str!("Sample Konfabulator Widget"))
+        );
+        assert_eq!(parsed.pointer(""), Ok(&parsed));
+
+        let err = parsed.pointer("/widget/window/missing/title").unwrap_err();
+        assert_eq!(err.kind(), PointerErrorKind::KeyNotFound);
+        assert_eq!(err.segment(), 2);
+        assert_eq!(err.token(), "missing");
+        let err = parsed.pointer("/widget/debug/on").unwrap_err();
+        assert_eq!(err.kind(), PointerErrorKind::NotAContainer);
+        assert_eq!(err.segment(), 2);
+        assert_eq!(
+            parsed.pointer("widget").unwrap_err().kind(),
+            PointerErrorKind::MissingLeadingSlash
+        );
+        assert_eq!(
+            parsed.pointer("/wid~2get").unwrap_err().kind(),
+            PointerErrorKind::InvalidEscape
+        );
+
+        *parsed.pointer_mut("/widget/debug").unwrap() = json_str!("off");
+        assert_eq!(parsed.pointer("/widget/debug"), Ok(&json_str!("off")));
+
+        assert_eq!(parsed.pointer_insert("/a~1b", json_arr!(vec![])), Ok(None));
+        assert_eq!(
+            parsed.pointer_insert("/a~1b/-", json_num!(2; uint)),
+            Ok(None)
+        );
+        assert_eq!(
+            parsed.pointer_insert("/a~1b/0", json_num!(1; uint)),
+            Ok(None)
+        );
+        assert_eq!(parsed.pointer_insert("/m~0n", JsonVal::Null), Ok(None));
+        assert_eq!(
+            parsed.pointer("/a~1b"),
+            Ok(&json_arr!(vec![json_num!(1; uint), json_num!(2; uint)]))
+        );
+        assert_eq!(parsed.pointer("/m~0n"), Ok(&JsonVal::Null));
+        assert_eq!(
+            parsed.pointer("/a~1b/01").unwrap_err().kind(),
+            PointerErrorKind::InvalidIndex
+        );
+        assert_eq!(
+            parsed.pointer("/a~1b/2").unwrap_err().kind(),
+            PointerErrorKind::IndexOutOfBounds
+        );
+        assert_eq!(
+            parsed
+                .pointer_insert("/a~1b/3", JsonVal::Null)
+                .unwrap_err()
+                .kind(),
+            PointerErrorKind::IndexOutOfBounds
+        );
+
+        assert_eq!(parsed.pointer_remove("/a~1b/0"), Ok(json_num!(1; uint)));
+        assert_eq!(parsed.pointer_remove("/m~0n"), Ok(JsonVal::Null));
+        assert_eq!(
+            parsed.pointer_remove("").unwrap_err().kind(),
+            PointerErrorKind::RemoveRoot
+        );
+        if let JsonVal::Object(ref obj) = parsed {
+            assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["widget", "a/b"]);
+        } else {
+            unreachable!("Must parse as an object, {:#?}", parsed)
+        }
+    }
+
+    #[test]
+    fn jsonpath() {
+        use jsonpath::{JsonPath, PathErrorKind};
+
+        // Example taken from RFC 9535
+        let input = r#"{ "store": {
+                "book": [
+                  { "category": "reference", "author": "Nigel Rees",
+                    "title": "Sayings of the Century", "price": 8.95 },
+                  { "category": "fiction", "author": "Evelyn Waugh",
+                    "title": "Sword of Honour", "price": 12.99 },
+                  { "category": "fiction", "author": "Herman Melville",
+                    "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+                  { "category": "fiction", "author": "J. R. R. Tolkien",
+                    "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+                ],
+                "bicycle": { "color": "red", "price": 399 }
+              }
+            }"#;
+        let parsed =
+            parser::parse(tokenizer::Tokenizer::from_str(input)).expect("Expected valid json");
+        let query = |path: &str| parsed.query(path).expect("Expected valid query");
+        let titles = |path: &str| {
+            query(path)
+                .into_iter()
+                .map(|node| node.val.pointer("/title").unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let cheap = query("$.store.book[?(@.price < 10)].title");
+        assert_eq!(cheap.len(), 2);
+        assert_eq!(cheap[0].path, "$['store']['book'][0]['title']");
+        assert_eq!(cheap[0].val, &json_str!("Sayings of the Century"));
+        assert_eq!(cheap[1].path, "$['store']['book'][2]['title']");
+        assert_eq!(cheap[1].val, &json_str!("Moby Dick"));
+
+        assert_eq!(query("$..author").len(), 4);
+        assert_eq!(query("$.store.*").len(), 2);
+        assert_eq!(query("$..*").len(), 27);
+        assert_eq!(query("$.store..price").len(), 5);
+        assert_eq!(query("$..book[-1]")[0].path, "$['store']['book'][3]");
+        assert_eq!(
+            titles("$..book[0,1]"),
+            ["\"Sayings of the Century\"", "\"Sword of Honour\""]
+        );
+        assert_eq!(titles("$..book[:2]"), titles("$..book[0,1]"));
+        assert_eq!(
+            titles("$.store.book[::-2]"),
+            ["\"The Lord of the Rings\"", "\"Sword of Honour\""]
+        );
+        assert_eq!(
+            titles("$..book[?@.isbn]"),
+            ["\"Moby Dick\"", "\"The Lord of the Rings\""]
+        );
+        assert_eq!(
+            titles("$..book[?@.price < $.store.bicycle.price && !(@.category == 'fiction')]"),
+            ["\"Sayings of the Century\""]
+        );
+        assert_eq!(
+            titles("$.store.book[?@.author == \"Nigel Rees\" || @.price > 20]").len(),
+            2
+        );
+        assert_eq!(query("$.store.book[?@.missing == $.nothing]").len(), 4);
+        assert_eq!(
+            query("$[\"store\"]['bicycle'][?@ == 'red']")[0].path,
+            "$['store']['bicycle']['color']"
+        );
+
+        // Escapes are decoded on both sides
+        let parsed = parser::parse(tokenizer::Tokenizer::from_str(
+            r#"[{"a": "x\"y", "a\"b": 1, "\u00e9": "\u00e9", "\ud83d\ude00\ud800": 2}]"#,
+        ))
+        .unwrap();
+        let query = |path: &str| parsed.query(path).expect("Expected valid query");
+        assert_eq!(query(r#"$[?@.a == "x\"y"]"#).len(), 1);
+        assert_eq!(query(r#"$[?@.a == 'x"y']"#).len(), 1);
+        assert_eq!(query(r#"$[?@.a == 'x\\"y']"#).len(), 0);
+        assert_eq!(query(r#"$[?@.a > 'x!']"#).len(), 1);
+        let nodes = query(r#"$[0]['a"b']"#);
+        assert_eq!(nodes[0].val, &json_num!(1; uint));
+        assert_eq!(nodes[0].path, r#"$[0]['a"b']"#);
+        let nodes = query("$[0]['é']");
+        assert_eq!(nodes[0].path, "$[0]['é']");
+        assert_eq!(query("$[?@['é'] == '\\u00e9']").len(), 1);
+        assert_eq!(query("$[0]['😀\u{fffd}']").len(), 1);
+
+        let error = |path: &str| JsonPath::compile(path).unwrap_err().kind();
+        assert_eq!(error("store"), PathErrorKind::MissingRoot);
+        assert_eq!(error("$.store["), PathErrorKind::UnexpectedEnd);
+        assert_eq!(error("$[01]"), PathErrorKind::InvalidNumber);
+        assert_eq!(error("$[?@ == 01]"), PathErrorKind::InvalidNumber);
+        assert_eq!(error("$[?@ == -01]"), PathErrorKind::InvalidNumber);
+        assert_eq!(error("$[?@ == 1.]"), PathErrorKind::InvalidNumber);
+        assert!(JsonPath::compile("$[?@ == -0]").is_ok());
+        assert_eq!(error("$[?@..price == 1]"), PathErrorKind::NonSingularQuery);
+        assert_eq!(error("$[?1]"), PathErrorKind::InvalidFilter);
+        assert_eq!(error("$['\\x']"), PathErrorKind::InvalidEscape);
+    }
+
+    #[test]
+    fn json_patch() {
+        use patch::PatchErrorKind;
+        use pointer::PointerErrorKind;
+
+        let parse = |s: &str| {
+            parser::parse(tokenizer::Tokenizer::from_str(s)).expect("Expected valid json")
+        };
+        let mut doc = parse(r#"{"foo": ["bar", "baz"], "qux": {"a": 1}}"#);
+        let patch = parse(
+            r#"[
+                {"op": "test", "path": "/qux/a", "value": 1.0},
+                {"op": "add", "path": "/foo/1", "value": "new"},
+                {"op": "remove", "path": "/foo/0"},
+                {"op": "replace", "path": "/qux/a", "value": [2]},
+                {"op": "move", "from": "/qux/a", "path": "/moved"},
+                {"op": "copy", "from": "/moved", "path": "/foo/-"}
+            ]"#,
+        );
+        doc.apply_patch(&patch).expect("Expected patch to apply");
+        assert_eq!(
+            doc,
+            parse(r#"{"foo": ["new", "baz", [2]], "qux": {}, "moved": [2]}"#)
+        );
+        assert_eq!(patch::Patch::from_json(&patch).unwrap().to_json(), patch);
+
+        // Failed patches leave the document untouched
+        let before = doc.clone();
+        let err = doc
+            .apply_patch(&parse(
+                r#"[{"op": "remove", "path": "/moved"}, {"op": "test", "path": "/foo/0", "value": "old"}]"#,
+            ))
+            .unwrap_err();
+        assert_eq!(err.op(), 1);
+        assert_eq!(err.kind(), &PatchErrorKind::TestFailed);
+        assert_eq!(doc, before);
+
+        let err = doc
+            .apply_patch(&parse(
+                r#"[{"op": "add", "path": "/missing/a", "value": 1}]"#,
+            ))
+            .unwrap_err();
+        assert!(
+            matches!(err.kind(), PatchErrorKind::Pointer(err) if err.kind() == PointerErrorKind::KeyNotFound)
+        );
+        let err = doc
+            .apply_patch(&parse(
+                r#"[{"op": "move", "from": "/qux", "path": "/qux/a"}]"#,
+            ))
+            .unwrap_err();
+        assert_eq!(err.kind(), &PatchErrorKind::MoveIntoChild);
+        let err = doc
+            .apply_patch(&parse(
+                r#"[{"op": "move", "from": "/missing", "path": "/missing"}]"#,
+            ))
+            .unwrap_err();
+        assert!(
+            matches!(err.kind(), PatchErrorKind::Pointer(err) if err.kind() == PointerErrorKind::KeyNotFound)
+        );
+        let err = doc
+            .apply_patch(&parse(
+                r#"[{"op": "test", "path": "/foo"}, {"op": "frobnicate", "path": ""}]"#,
+            ))
+            .unwrap_err();
+        assert_eq!((err.op(), err.kind()), (0, &PatchErrorKind::InvalidPatch));
+    }
+
+    #[test]
+    fn json_diff() {
+        use patch::PatchOp;
+
+        let parse = |s: &str| {
+            parser::parse(tokenizer::Tokenizer::from_str(s)).expect("Expected valid json")
+        };
+        let pairs = [
+            (r#"{"a": 1, "b": [1, 2, 3]}"#, r#"{"b": [1, 2, 3], "a": 1}"#),
+            (
+                r#"{"a": 1, "b": {"c": "d"}}"#,
+                r#"{"a": 2, "b": {"c": "e", "f/g": true}}"#,
+            ),
+            (r#"[1, 2, 3, 4, 5]"#, r#"[0, 1, 3, 5, 6]"#),
+            (
+                r#"[{"id": 1}, {"id": 2}, {"id": 3}]"#,
+                r#"[{"id": 1}, {"id": 2, "x": null}]"#,
+            ),
+            (r#"{"a": [1, 2]}"#, r#"{"a": {"b": [1, 2]}}"#),
+            (r#"[[1], [2], [3]]"#, r#"[[3], [2], [1], [0]]"#),
+        ];
+        for (from, to) in pairs {
+            let (mut from, to) = (parse(from), parse(to));
+            let patch = diff::diff(&from, &to);
+            patch.apply(&mut from).expect("Expected patch to apply");
+            assert_eq!(from, to);
+        }
+
+        // Too big for the LCS table, so elements are diffed by position
+        let mut from = JsonVal::Array((0..5000).map(|n| json_num!(n; uint)).collect());
+        let to = JsonVal::Array((0..4000).map(|n| json_num!(n * 2 + 1; uint)).collect());
+        let patch = diff::diff(&from, &to);
+        assert_eq!(patch.0.len(), 5000);
+        patch.apply(&mut from).expect("Expected patch to apply");
+        assert_eq!(from, to);
+
+        let patch = diff::diff(&parse(pairs[0].0), &parse(pairs[0].1));
+        assert!(patch.0.is_empty());
+        let patch = diff::diff(&parse(pairs[1].0), &parse(pairs[1].1));
+        assert_eq!(
+            patch.0,
+            vec![
+                PatchOp::Replace {
+                    path: "/a".to_string(),
+                    value: json_num!(2; uint)
+                },
+                PatchOp::Replace {
+                    path: "/b/c".to_string(),
+                    value: json_str!("e")
+                },
+                PatchOp::Add {
+                    path: "/b/f~1g".to_string(),
+                    value: JsonVal::Boolean(true)
+                },
+            ]
+        );
+        let patch = diff::diff(&parse(pairs[2].0), &parse(pairs[2].1));
+        assert_eq!(
+            patch.0,
+            vec![
+                PatchOp::Add {
+                    path: "/0".to_string(),
+                    value: json_num!(0; uint)
+                },
+                PatchOp::Remove {
+                    path: "/2".to_string()
+                },
+                PatchOp::Remove {
+                    path: "/3".to_string()
+                },
+                PatchOp::Add {
+                    path: "/4".to_string(),
+                    value: json_num!(6; uint)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn json_merge_patch() {
+        let parse = |s: &str| {
+            parser::parse(tokenizer::Tokenizer::from_str(s)).expect("Expected valid json")
+        };
+
+        // Example taken from RFC 7386
+        let mut doc = parse(
+            r#"{
+                "title": "Goodbye!",
+                "author" : { "givenName" : "John", "familyName" : "Doe" },
+                "tags":[ "example", "sample" ],
+                "content": "This will be unchanged"
+            }"#,
+        );
+        let patch = parse(
+            r#"{
+                "title": "Hello!",
+                "phoneNumber": "+01-123-456-7890",
+                "author": { "familyName": null },
+                "tags": [ "example" ]
+            }"#,
+        );
+        let expected = parse(
+            r#"{
+                "title": "Hello!",
+                "author" : { "givenName" : "John" },
+                "tags": [ "example" ],
+                "content": "This will be unchanged",
+                "phoneNumber": "+01-123-456-7890"
+            }"#,
+        );
+        let original = doc.clone();
+        doc.merge_patch(&patch);
+        assert_eq!(doc, expected);
+        if let JsonVal::Object(ref doc) = doc {
+            assert_eq!(
+                doc.keys().collect::<Vec<_>>(),
+                vec!["title", "author", "tags", "content", "phoneNumber"]
+            );
+        }
+
+        let generated = merge_patch::diff(&original, &expected);
+        assert_eq!(generated, patch);
+        let mut doc = original;
+        doc.merge_patch(&generated);
+        assert_eq!(doc, expected);
+
+        let mut doc = json_arr!(vec![]);
+        doc.merge_patch(&parse(r#"{"a": {"b": null, "c": 1}}"#));
+        assert_eq!(doc, parse(r#"{"a": {"c": 1}}"#));
+    }
+
+    #[test]
+    fn json_schema() {
+        use schema::{Schema, SchemaErrorKind, ValidationErrorKind};
+
+        let parse = |s: &str| {
+            parser::parse(tokenizer::Tokenizer::from_str(s)).expect("Expected valid json")
+        };
+        let schema = parse(
+            r##"{
+                "type": "object",
+                "required": ["firstName", "age", "address"],
+                "properties": {
+                    "firstName": { "type": "string", "minLength": 1, "pattern": "^[A-Z]" },
+                    "age": { "type": "integer", "minimum": 0, "exclusiveMaximum": 150 },
+                    "isAlive": { "const": true },
+                    "address": { "$ref": "#/$defs/address" },
+                    "phoneNumbers": {
+                        "type": "array",
+                        "maxItems": 2,
+                        "items": {
+                            "type": "object",
+                            "properties": { "type": { "enum": ["home", "office"] } }
+                        }
+                    },
+                    "spouse": { "anyOf": [{ "type": "null" }, { "$ref": "#" }] },
+                    "children": { "oneOf": [{ "type": "array" }, { "type": ["array", "null"] }] }
+                },
+                "$defs": {
+                    "address": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "streetAddress": { "type": "string" },
+                            "city": { "type": "string" },
+                            "state": { "type": "string", "maxLength": 2 },
+                            "postalCode": { "type": "string" }
+                        }
+                    }
+                }
+            }"##,
+        );
+        let schema = Schema::compile(&schema).expect("Expected valid schema");
+
+        let input = include_str!("../tests/wikipedia.json");
+        let mut doc = parse(input);
+        doc.pointer_remove("/children").unwrap();
+        assert_eq!(schema.validate(&doc), Ok(()));
+
+        let mut doc = parse(input);
+        *doc.pointer_mut("/age").unwrap() = json_num!(27.5; float);
+        *doc.pointer_mut("/address/state").unwrap() = json_str!("New York");
+        doc.pointer_insert("/address/country", json_str!("US"))
+            .unwrap();
+        doc.pointer_insert("/phoneNumbers/0/type", json_str!("mobile"))
+            .unwrap();
+        doc.pointer_remove("/firstName").unwrap();
+        let errors = schema.validate(&doc).unwrap_err();
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|err| (err.kind().clone(), err.instance_path(), err.schema_path()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    ValidationErrorKind::Required("firstName".to_string()),
+                    "",
+                    "/required"
+                ),
+                (ValidationErrorKind::Type, "/age", "/properties/age/type"),
+                (
+                    ValidationErrorKind::AdditionalProperty("country".to_string()),
+                    "/address/country",
+                    "/$defs/address/additionalProperties"
+                ),
+                (
+                    ValidationErrorKind::MaxLength,
+                    "/address/state",
+                    "/$defs/address/properties/state/maxLength"
+                ),
+                (
+                    ValidationErrorKind::Enum,
+                    "/phoneNumbers/0/type",
+                    "/properties/phoneNumbers/items/properties/type/enum"
+                ),
+                (
+                    ValidationErrorKind::OneOf(2),
+                    "/children",
+                    "/properties/children/oneOf"
+                ),
+            ]
+        );
+
+        let err = Schema::compile(&parse(r##"{"$ref": "#/$defs/missing"}"##)).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.location()),
+            (SchemaErrorKind::UnresolvedRef, "/$ref")
+        );
+        let err = Schema::compile(&parse(r#"{"minimum": "1"}"#)).unwrap_err();
+        assert_eq!(err.kind(), SchemaErrorKind::InvalidKeyword);
+        let err = Schema::compile(&parse(r#"{"minLength": 1.5}"#)).unwrap_err();
+        assert_eq!(err.kind(), SchemaErrorKind::InvalidKeyword);
+        let err = Schema::compile(&parse(r#"{"maxItems": -1.0}"#)).unwrap_err();
+        assert_eq!(err.kind(), SchemaErrorKind::InvalidKeyword);
+
+        // Strings are checked once their escapes are decoded, and counts can
+        // be written as floats
+        let string = |s: &str| parser::parse_value(tokenizer::Tokenizer::from_str(s)).unwrap();
+        let input = r#"{"minLength": 2.0, "maxLength": 2, "pattern": "^\\d\\u00e9$"}"#;
+        let schema = Schema::compile(&parse(input)).expect("Expected a valid schema");
+        assert!(schema.is_valid(&string(r#""1\u00e9""#)));
+        assert!(schema.is_valid(&string(r#""1é""#)));
+        assert!(!schema.is_valid(&string(r#""\u0031\u00e9x""#)));
+        assert!(!schema.is_valid(&string(r#""\\d\u00e9""#)));
+        let schema = Schema::compile(&parse(r#"{"maxLength": 1}"#)).unwrap();
+        assert!(schema.is_valid(&string(r#""\n""#)));
+        assert!(!schema.is_valid(&string(r#""\\n""#)));
+
+        // References that loop without going into the instance
+        let err = Schema::compile(&parse(r##"{"$ref": "#"}"##)).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.location()),
+            (SchemaErrorKind::RecursiveRef, "/$ref")
+        );
+        let input = r##"{"$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##;
+        let err = Schema::compile(&parse(input)).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.location()),
+            (SchemaErrorKind::RecursiveRef, "/$defs/b/$ref")
+        );
+        let err = Schema::compile(&parse(r##"{"anyOf": [{"not": {"$ref": "#"}}]}"##)).unwrap_err();
+        assert_eq!(err.kind(), SchemaErrorKind::RecursiveRef);
+        // Going into the instance first is fine
+        let input = r##"{"items": {"$ref": "#"}, "type": "array"}"##;
+        let schema = Schema::compile(&parse(input)).expect("Expected a valid schema");
+        assert!(schema.is_valid(&parse("[[], [[]]]")));
+        assert!(!schema.is_valid(&parse("[[1]]")));
+    }
+
+    #[test]
+    fn jsonc() {
+        use tokenizer::ParsingErrorKind;
+
+        let input = r#"{
+            // Line comment
+            "editor.fontSize": 14, /* block
+               comment spanning lines */ "files.exclude": {
+                "**/.git": true, // trailing comma below
+            },
+            "list": [1, 2, /* inline */ 3,],
+        }"#;
+        let jsonc = Options {
+            comments: true,
+            trailing_commas: true,
+            ..Options::default()
+        };
+        let parsed = parser::parse(tokenizer::Tokenizer::from_str(input).with_options(jsonc))
+            .expect("Expected valid jsonc");
+        if let JsonVal::Object(ref parsed) = parsed {
+            assert_eq!(parsed["editor.fontSize"], json_num!(14; uint));
+            assert_eq!(
+                parsed["files.exclude"],
+                json_obj!({"**/.git", JsonVal::Boolean(true)})
+            );
+            assert_eq!(
+                parsed["list"],
+                json_arr!(vec![
+                    json_num!(1; uint),
+                    json_num!(2; uint),
+                    json_num!(3; uint)
+                ])
+            );
+        } else {
+            unreachable!("Must parse as an object, {:#?}", parsed)
+        }
+
+        // Comments are rejected unless enabled
+        assert!(parser::parse(tokenizer::Tokenizer::from_str(input)).is_err());
+        let comments_only = Options {
+            comments: true,
+            ..Options::default()
+        };
+        let err = parser::parse(tokenizer::Tokenizer::from_str(input).with_options(comments_only))
+            .unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidTrailingComma));
+
+        // Locations keep counting through block comments
+        let err = parser::parse(
+            tokenizer::Tokenizer::from_str("{/* one\n two */ \"a\": tru}").with_options(jsonc),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidToken));
+        assert_eq!((err.loc.line, err.loc.col), (2, 14));
+
+        let err = parser::parse(tokenizer::Tokenizer::from_str("[1, /* open").with_options(jsonc))
+            .unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnterminatedComment));
+        assert_eq!((err.loc.line, err.loc.col), (1, 5));
+        let err = parser::parse(tokenizer::Tokenizer::from_str("[1, / 2]").with_options(jsonc))
+            .unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidComment));
+    }
+
+    #[test]
+    fn json5() {
+        use tokenizer::ParsingErrorKind;
+
+        // Example taken from https://json5.org
+        let input = r#"{
+            // comments
+            unquoted: 'and you can quote me on that',
+            singleQuotes: 'I can use "double quotes" here',
+            lineBreaks: "Look, Mom! \
+No \\n's!",
+            hexadecimal: 0xdecaf,
+            leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+            positiveSign: +1,
+            trailingComma: 'in objects', andIn: ['arrays',],
+            "backwardsCompatible": "with JSON",
+            infinities: [Infinity, -Infinity, NaN],
+            escapes: 'it\'s \x41\v',
+        }"#;
+        let json5 = Options {
+            json5: true,
+            ..Options::default()
+        };
+        let parsed = parser::parse(tokenizer::Tokenizer::from_str(input).with_options(json5))
+            .expect("Expected valid json5");
+        if let JsonVal::Object(ref obj) = parsed {
+            assert_eq!(obj["unquoted"], json_str!("and you can quote me on that"));
+            assert_eq!(
+                obj["singleQuotes"],
+                json_str!(r#"I can use \"double quotes\" here"#)
+            );
+            assert_eq!(obj["lineBreaks"], json_str!(r"Look, Mom! No \\n's!"));
+            assert_eq!(obj["hexadecimal"], json_num!(0xdecaf; uint));
+            assert_eq!(obj["leadingDecimalPoint"], json_num!(0.8675309; float));
+            assert_eq!(obj["andTrailing"], json_num!(8675309.0; float));
+            assert_eq!(obj["positiveSign"], json_num!(1; uint));
+            assert_eq!(obj["andIn"], json_arr!(vec![json_str!("arrays")]));
+            assert_eq!(obj["backwardsCompatible"], json_str!("with JSON"));
+            assert_eq!(
+                obj["infinities"],
+                json_arr!(vec![
+                    json_num!(f64::INFINITY; float),
+                    json_num!(f64::NEG_INFINITY; float),
+                    json_num!(f64::NAN; float),
+                ])
+            );
+            assert_eq!(obj["escapes"], json_str!(r"it's \u0041\u000b"));
+        } else {
+            unreachable!("Must parse as an object, {:#?}", parsed)
+        }
+
+        let text = json5::to_string(&parsed);
+        assert!(text.contains("    unquoted: \"and you can quote me on that\",\n"));
+        assert!(text.contains(
+            "    infinities: [\n        Infinity,\n        -Infinity,\n        NaN\n    ],\n"
+        ));
+        let reparsed = parser::parse(tokenizer::Tokenizer::from_str(&text).with_options(json5))
+            .expect("Expected valid json5");
+        assert_eq!(reparsed, parsed);
+
+        // Escapes must be complete, and control characters escaped, so that
+        // strings always serialize as valid JSON
+        let json5_val =
+            |s: &str| parser::parse_value(tokenizer::Tokenizer::from_str(s).with_options(json5));
+        for input in [
+            r"'\x'",
+            r"'\x4'",
+            r"'\xZZ'",
+            r"'\u12'",
+            r#""\uZZZZ""#,
+            "'a\u{1}b'",
+            "'a\tb'",
+            r"'\01'",
+            r"'\7'",
+        ] {
+            let err = json5_val(input).unwrap_err();
+            assert!(
+                matches!(err.kind, ParsingErrorKind::InvalidToken),
+                "{input}"
+            );
+        }
+        let val = json5_val(r"'\x41\u00e9\0'").unwrap();
+        assert_eq!(val, json_str!(r"\u0041\u00e9\u0000"));
+        // An escaped control character is itself, escaped the JSON way
+        let val = json5_val("'a\\\tb'").unwrap();
+        assert_eq!(val, json_str!(r"a\u0009b"));
+        let text = val.to_string();
+        assert_eq!(
+            parser::parse_value(tokenizer::Tokenizer::from_str(&text)).unwrap(),
+            val
+        );
+
+        // None of this is valid JSON
+        assert!(parser::parse(tokenizer::Tokenizer::from_str(input)).is_err());
+        assert!(parser::parse(tokenizer::Tokenizer::from_str("[0x10]")).is_err());
+        assert!(parser::parse(tokenizer::Tokenizer::from_str("['a']")).is_err());
+    }
+
+    #[test]
+    fn ndjson() {
+        use ndjson::{NdjsonErrorKind, NdjsonReader, NdjsonWriter};
+        use tokenizer::ParsingErrorKind;
+
+        let input = "{\"id\": 1, \"tags\": [\"a\", \"b\"]}\n\
+                     \n\
+                     {\"id\": 2,}\n\
+                     \"just a string\"\r\n\
+                     [1, 2] 3\n\
+                     42\n";
+        let mut reader = NdjsonReader::new(input.as_bytes());
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            json_obj!({"id", json_num!(1; uint)}, {"tags", json_arr!(vec![json_str!("a"), json_str!("b")])})
+        );
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.line(), 3);
+        assert!(matches!(
+            err.kind(),
+            NdjsonErrorKind::Parsing(err) if matches!(err.kind, ParsingErrorKind::InvalidTrailingComma)
+        ));
+        assert_eq!(reader.next().unwrap().unwrap(), json_str!("just a string"));
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.line(), 5);
+        assert_eq!(reader.next().unwrap().unwrap(), json_num!(42; uint));
+        assert!(reader.next().is_none());
+
+        // A line that isn't UTF-8 doesn't stop the reading
+        let mut reader = NdjsonReader::new(&b"1\n\xff\xfe\n2\n3\n"[..]);
+        assert_eq!(reader.next().unwrap().unwrap(), json_num!(1; uint));
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.line(), 2);
+        assert!(
+            matches!(err.kind(), NdjsonErrorKind::Io(err) if err.kind() == std::io::ErrorKind::InvalidData)
+        );
+        assert_eq!(reader.next().unwrap().unwrap(), json_num!(2; uint));
+        assert_eq!(reader.next().unwrap().unwrap(), json_num!(3; uint));
+        assert!(reader.next().is_none());
+
+        // Same as `parse_value`, only JSON's whitespace is allowed around values
+        let mut reader = NdjsonReader::new("\u{a0}1\n2\u{2028}\n".as_bytes());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        let options = Options {
+            trailing_commas: true,
+            ..Default::default()
+        };
+        let reader = NdjsonReader::new(input.as_bytes()).with_options(options);
+        assert_eq!(reader.filter(Result::is_ok).count(), 4);
+
+        // Too long lines are skipped, and reading goes on after them
+        let long = format!("[{}1]", "1, ".repeat(10_000));
+        let lines = format!("[1, 2, 345]\n{long}\n \"abc\"\r\n[1, 2, 3456]\n{long}");
+        let mut reader = NdjsonReader::new(lines.as_bytes()).with_max_line_length(11);
+        assert_eq!(
+            reader.next().unwrap().unwrap().to_compact_string(),
+            "[1,2,345]"
+        );
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind(), NdjsonErrorKind::LineTooLong));
+        assert_eq!(err.line(), 2);
+        assert_eq!(err.to_string(), "line 2: too long");
+        assert_eq!(reader.next().unwrap().unwrap(), json_str!("abc"));
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind(), NdjsonErrorKind::LineTooLong));
+        assert_eq!(err.line(), 4);
+        assert_eq!(reader.next().unwrap().unwrap_err().line(), 5);
+        assert!(reader.next().is_none());
+
+        let mut writer = NdjsonWriter::new(Vec::new());
+        let vals: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .filter_map(Result::ok)
+            .collect();
+        for val in &vals {
+            writer.write(val).unwrap();
+        }
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "{\"id\":1,\"tags\":[\"a\",\"b\"]}\n\"just a string\"\n42\n"
+        );
+        let reread: Vec<_> = NdjsonReader::new(output.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reread, vals);
+    }
+
+    #[test]
+    fn stream() {
+        use stream::StreamParser;
+        use tokenizer::{ParsingErrorKind, Tokenizer};
+
+        let input = "{\"a\":1}{\"b\":2} [true]\n\"é\" 3 null";
+        let vals: Vec<_> = StreamParser::new(Tokenizer::from_str(input))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            vals,
+            vec![
+                (0, json_obj!({"a", json_num!(1; uint)})),
+                (7, json_obj!({"b", json_num!(2; uint)})),
+                (15, json_arr!(vec![JsonVal::Boolean(true)])),
+                (22, json_str!("é")),
+                (27, json_num!(3; uint)),
+                (29, JsonVal::Null),
+            ]
+        );
+
+        // RFC 7464 JSON text sequences
+        let input = "\u{1e}{\"a\":1}\n\u{1e}[1, 2]\n\u{1e}\"x\"\n";
+        let offsets: Vec<_> = StreamParser::new(Tokenizer::from_str(input))
+            .map(|res| res.unwrap().0)
+            .collect();
+        assert_eq!(offsets, vec![1, 10, 18]);
+
+        // Values inside arrays still need commas, and errors end the stream
+        let mut parser = StreamParser::new(Tokenizer::from_str("[1] [1 2] [3]"));
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            (0, json_arr!(vec![json_num!(1; uint)]))
+        );
+        let err = parser.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::MissingEndingComma));
+        assert!(parser.next().is_none());
+
+        assert!(StreamParser::new(Tokenizer::from_str(" \n "))
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn borrowed() {
+        use std::borrow::Cow;
+        use tokenizer::ParsingErrorKind;
+
+        let input = r#"{"plain": "text", "esc\"aped": "a\nb\u00e9\ud83d\ude00", "n": [-1, 2.5]}"#;
+        let JsonValRef::Object(obj) = borrowed::parse(input).unwrap() else {
+            panic!("Expected an object");
+        };
+        let keys: Vec<_> = obj.keys().collect();
+        assert!(matches!(keys[0], Cow::Borrowed("plain")));
+        assert!(matches!(keys[1], Cow::Owned(key) if key == "esc\"aped"));
+        assert!(matches!(
+            &obj["plain"],
+            JsonValRef::String(Cow::Borrowed("text"))
+        ));
+        assert!(matches!(
+            &obj["esc\"aped"],
+            JsonValRef::String(Cow::Owned(s)) if s == "a\nb\u{e9}\u{1f600}"
+        ));
+        assert_eq!(
+            obj["n"],
+            JsonValRef::Array(vec![
+                JsonValRef::Number(Number::SignedInt(-1)),
+                JsonValRef::Number(Number::Float(2.5))
+            ])
+        );
+
+        // Same result as the regular parser
+        for input in [
+            include_str!("../tests/wikipedia.json"),
+            include_str!("../tests/jsonplaceholder.json"),
+            include_str!("../tests/jsonorg.json"),
+        ] {
+            let expected = parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap();
+            assert_eq!(JsonVal::from(borrowed::parse(input).unwrap()), expected);
+        }
+        // Escapes are normalized when converting
+        let input = r#"["\u00e9\/\u0041", "\"\u0001"]"#;
+        assert_eq!(
+            JsonVal::from(borrowed::parse(input).unwrap()),
+            json_arr!(vec![json_str!("\u{e9}/A"), json_str!("\\\"\\u0001")])
+        );
+        assert_eq!(
+            parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap(),
+            json_arr!(vec![
+                json_str!("\\u00e9\\/\\u0041"),
+                json_str!("\\\"\\u0001")
+            ])
+        );
+
+        let err = borrowed::parse("[1,\n 2,]").unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidTrailingComma));
+        assert_eq!((err.loc.line, err.loc.col), (2, 3));
+        let err = borrowed::parse("{\"a\": \"\\x\"}").unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidToken));
+        assert_eq!((err.loc.line, err.loc.col), (1, 8));
+        assert!(borrowed::parse("[1 2]").is_err());
+        assert!(borrowed::parse("\"\\ud800\"").is_err());
+    }
+
+    #[test]
+    fn bytes() {
+        use tokenizer::ParsingErrorKind;
+
+        for input in [
+            include_str!("../tests/wikipedia.json"),
+            include_str!("../tests/jsonplaceholder.json"),
+            include_str!("../tests/jsonorg.json"),
+            "[\"a\\\"b\\u00e9\", -1, 2.5e3, true, false, null, {}, []]",
+            " \"é\" ",
+            "[-0, 0, -9223372036854775808, 18446744073709551615, 1e2]",
+        ] {
+            let expected = parser::parse_value(tokenizer::Tokenizer::from_str(input)).unwrap();
+            assert_eq!(bytes::parse(input.as_bytes()).unwrap(), expected);
+        }
+
+        let err = bytes::parse(b"{\"a\": 1,\n \"b\": 2,}").unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidTrailingComma));
+        assert_eq!((err.loc.line, err.loc.col), (2, 8));
+        let err = bytes::parse("[\"é\", tru]".as_bytes()).unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidToken));
+        assert_eq!((err.loc.line, err.loc.col), (1, 7));
+        let err = bytes::parse(b"[\"\xff\"]").unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnsupportedToken));
+        assert_eq!((err.loc.line, err.loc.col), (1, 3));
+        assert!(bytes::parse(b"[1 2]").is_err());
+        assert!(bytes::parse(b"\"\\x\"").is_err());
+        assert!(bytes::parse(b"01").is_err());
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd() {
+        use simd::{structural_index, Backend};
+        use tokenizer::ParsingErrorKind;
+
+        let files = [
+            include_str!("../tests/wikipedia.json"),
+            include_str!("../tests/jsonplaceholder.json"),
+            include_str!("../tests/jsonorg.json"),
+        ];
+        for input in files {
+            let expected = parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap();
+            assert_eq!(simd::parse(input.as_bytes()).unwrap(), expected);
+            let doc = simd::parse_tape(input.as_bytes()).unwrap();
+            assert_eq!(JsonVal::from(&doc), expected);
+        }
+
+        // Long strings and escapes crossing the 64 byte blocks
+        let long = "x".repeat(61);
+        let inputs = [
+            format!("[\"{long}\\\"\", \"\\\\\", 1]"),
+            format!("[\"{long}\\\\\", \"a\\\"b\", 1]"),
+            format!("[\"{long}ab\\\\\\\"c\", true]"),
+            format!("{{\"{long}\": [1, 2, 3], \"b\": {{\"c\": null}}}}"),
+            format!("[{long}1]"),
+            "[-0, 12345678901234567890, 1.5e-3, \"é\", false]".to_string(),
+            "[1, 2,]".to_string(),
+            "[1 2]".to_string(),
+            "{\"a\" 1}".to_string(),
+            "{\"a\": 1,\n\"b\": 2,}".to_string(),
+            "[\"abc]".to_string(),
+            "[\"a\\x\"]".to_string(),
+            "[truex, 1]".to_string(),
+            "[true\0]".to_string(),
+            "[null\u{1}, 1]".to_string(),
+            "[false}".to_string(),
+            "[\"a\tb\"]".to_string(),
+            format!("[\"{long}\u{1}\", \"ok\"]"),
+            "[\"a\\\"\", \"b\\x".to_string(),
+            "[1x]".to_string(),
+            "[\"a\"x]".to_string(),
+            "[1] 2".to_string(),
+            "[".to_string(),
+            "".to_string(),
+            " 42 ".to_string(),
+            "2nt".to_string(),
+        ];
+        for input in &inputs {
+            let bytes = input.as_bytes();
+            let backends = [
+                Backend::Scalar,
+                #[cfg(target_arch = "x86_64")]
+                Backend::Sse2,
+            ];
+            for backend in backends {
+                assert_eq!(
+                    structural_index(bytes, backend),
+                    structural_index(bytes, Backend::detect())
+                );
+            }
+            same_as_parser(input);
+        }
+
+        // Every character of a file broken in a few ways
+        let file = files[0];
+        for (i, c) in file.char_indices() {
+            for replacement in ["", "\"", "\\", ",", "]", "}", "x", "-", "\u{1}"] {
+                let end = i + c.len_utf8();
+                same_as_parser(&format!("{}{replacement}{}", &file[..i], &file[end..]));
+            }
+        }
+
+        let err = simd::parse(b"[\"\xff\"]").unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnsupportedToken));
+    }
+
+    // Values and tapes built from the index must be those of `parser::parse`,
+    // errors included
+    #[cfg(feature = "simd")]
+    fn same_as_parser(input: &str) {
+        let expected = parser::parse(tokenizer::Tokenizer::from_str(input));
+        let tape = simd::parse_tape(input.as_bytes()).map(|doc| JsonVal::from(&doc));
+        for res in [simd::parse(input.as_bytes()), tape] {
+            match (res, &expected) {
+                (Ok(val), Ok(expected)) => assert_eq!(&val, expected, "{input}"),
+                (Err(err), Err(expected)) => {
+                    assert_eq!(format!("{err}"), format!("{expected}"), "{input}")
+                }
+                (res, expected) => panic!("{input}: {res:?} != {expected:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn tape() {
+        use tape::Document;
+
+        for input in [
+            include_str!("../tests/wikipedia.json"),
+            include_str!("../tests/jsonplaceholder.json"),
+            include_str!("../tests/jsonorg.json"),
+        ] {
+            let expected = parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap();
+            let doc = Document::parse(input.as_bytes()).unwrap();
+            assert_eq!(JsonVal::from(&doc), expected);
+        }
+
+        let input = include_str!("../tests/wikipedia.json");
+        let doc = Document::parse(input.as_bytes()).unwrap();
+        let root = doc.root();
+        assert!(root.is_object());
+        assert_eq!(root.get("firstName").unwrap().as_str(), Some("John"));
+        assert_eq!(root.get("isAlive").unwrap().as_bool(), Some(true));
+        assert_eq!(
+            root.get("age").unwrap().as_number(),
+            Some(&Number::UnsignedInt(27))
+        );
+        assert!(root.get("spouse").unwrap().is_null());
+        let phones = root.get("phoneNumbers").unwrap();
+        assert_eq!(phones.len(), Some(2));
+        assert_eq!(
+            phones.at(1).unwrap().get("number").unwrap().as_str(),
+            Some("646 555-4567")
+        );
+        assert!(phones.at(2).is_none());
+        assert!(phones.get("type").is_none());
+        let keys: Vec<_> = root.members().map(|(key, _)| key).collect();
+        assert_eq!(keys[..3], ["firstName", "lastName", "isAlive"]);
+        assert_eq!(
+            JsonVal::from(root.get("children").unwrap()),
+            json_arr!(vec![])
+        );
+
+        // The last duplicate key wins, like in `JsonVal`
+        let doc = Document::parse(b"{\"a\": 1, \"b\": [], \"a\": 2}").unwrap();
+        assert_eq!(
+            doc.root().get("a").unwrap().as_number(),
+            Some(&Number::UnsignedInt(2))
+        );
+        assert_eq!(doc.root().len(), Some(3));
+        assert_eq!(
+            JsonVal::from(&doc),
+            json_obj!({"a", json_num!(2; uint)}, {"b", json_arr!(vec![])})
+        );
+
+        assert!(Document::parse(b"[1, [2,]]").is_err());
+    }
+
+    #[test]
+    fn ondemand() {
+        use ondemand::{Document, OndemandError, OndemandErrorKind};
+        use tokenizer::ParsingErrorKind;
+
+        let input = include_str!("../tests/jsonplaceholder.json");
+        let doc = Document::from_str(input);
+        let todo = doc.at(3).unwrap();
+        assert_eq!(
+            todo.get("title").unwrap().as_str().unwrap(),
+            "et porro tempora"
+        );
+        assert_eq!(
+            todo.get("completed").unwrap().to_json_val().unwrap(),
+            JsonVal::Boolean(true)
+        );
+        let expected = parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap();
+        let JsonVal::Array(todos) = &expected else {
+            panic!("Expected an array");
+        };
+        assert_eq!(todo.to_json_val().unwrap(), todos[3]);
+        let elements: Vec<_> = doc
+            .elements()
+            .map(|todo| todo.unwrap().to_json_val().unwrap())
+            .collect();
+        assert_eq!(&elements, todos);
+
+        // Steps chain with `?`, parsing errors included
+        let doc = Document::from_str("{\"users\": [{\"a\": 1}, {\"email\": \"x@y\"}]}");
+        let email = || -> Result<&str, OndemandError> {
+            Ok(doc.get("users")?.at(1)?.get("email")?.as_str()?)
+        };
+        assert_eq!(email().unwrap(), "x@y");
+
+        // Missing keys and indices have their own kinds, at the container
+        let users = doc.get("users").unwrap();
+        let err = users.at(2).unwrap_err();
+        assert_eq!(err.kind(), OndemandErrorKind::IndexOutOfBounds);
+        assert_eq!((err.loc().line(), err.loc().col()), (1, 11));
+        let err = users.at(0).unwrap().get("b").unwrap_err();
+        assert_eq!(err.kind(), OndemandErrorKind::KeyNotFound);
+        assert_eq!((err.loc().line(), err.loc().col()), (1, 12));
+        let err = Document::from_str("[]").at(0).unwrap_err();
+        assert_eq!(err.kind(), OndemandErrorKind::IndexOutOfBounds);
+        assert_eq!(Document::from_str(" [ ] ").elements().count(), 0);
+
+        // Errors have the location of the value that failed
+        let err = users.get("a").unwrap_err();
+        let kind = OndemandErrorKind::Parsing(ParsingErrorKind::UnexpectedToken);
+        assert_eq!(err.kind(), kind);
+        assert_eq!((err.loc().line(), err.loc().col()), (1, 11));
+
+        // Only the text that is scanned is checked. Objects are scanned to
+        // the end, for duplicate keys, arrays only up to the element.
+        let doc = Document::from_str("{\"users\": [{\"a\": 1}], \"b\": [1, 2,]}");
+        let err = doc.get("users").unwrap_err();
+        let kind = OndemandErrorKind::Parsing(ParsingErrorKind::InvalidTrailingComma);
+        assert_eq!(err.kind(), kind);
+        assert_eq!((err.loc().line(), err.loc().col()), (1, 33));
+        assert!(doc.get_first("users").is_ok());
+        let doc = Document::from_str("[{\"a\": 1}, [1, 2,], 3]");
+        assert!(doc.at(0).is_ok());
+        assert!(doc.at(1).is_ok());
+        assert!(doc.at(2).is_err());
+        // The iterator stops at the first error
+        let results: Vec<_> = doc.elements().map(|res| res.is_ok()).collect();
+        assert_eq!(results, [true, true, false]);
+
+        // With duplicate keys `get` uses the last one, like `JsonVal` and
+        // `tape`, and `get_first` the first one, scanning only up to it
+        let doc = Document::from_str("{\"a\": 1, \"a\": 2}");
+        let a = doc.get("a").unwrap().to_json_val().unwrap();
+        assert_eq!(a, json_num!(2; uint));
+        let a = doc.get_first("a").unwrap().to_json_val().unwrap();
+        assert_eq!(a, json_num!(1; uint));
+        let doc = Document::from_str("{\"a\": 1, \"a\": 2, \"b\": }");
+        assert!(doc.get("a").is_err());
+        assert!(doc.get_first("a").is_ok());
+    }
+
+    #[test]
+    fn push() {
+        use push::{Event, PushParser};
+        use tokenizer::ParsingErrorKind;
+
+        for input in [
+            include_str!("../tests/wikipedia.json"),
+            include_str!("../tests/jsonplaceholder.json"),
+            include_str!("../tests/jsonorg.json"),
+            "[\"a\\\"b\\u00e9\", \"é\", -1, 2.5e3, true, null, {}, []]",
+        ] {
+            let expected = parser::parse_value(tokenizer::Tokenizer::from_str(input)).unwrap();
+            for size in [1, 2, 7, 64, input.len()] {
+                let mut parser = PushParser::new();
+                let mut val = None;
+                for chunk in input.as_bytes().chunks(size) {
+                    parser.feed(chunk);
+                    if let Some(v) = parser.value().unwrap() {
+                        assert!(val.replace(v).is_none());
+                    }
+                }
+                assert_eq!(val.as_ref(), Some(&expected));
+            }
+        }
+
+        let mut parser = PushParser::new();
+        parser.feed(b"{\"a\": [1, \"x");
+        let mut events = Vec::new();
+        loop {
+            match parser.next_event().unwrap() {
+                Event::NeedMoreData => break,
+                event => events.push(event),
+            }
+        }
+        parser.feed("y\\\"\"], \"b\": 12".as_bytes());
+        while let Ok(event) = parser.next_event() {
+            if event == Event::NeedMoreData {
+                break;
+            }
+            events.push(event);
+        }
+        // The number could still go on
+        assert_eq!(events.last(), Some(&Event::Key("b".to_string())));
+        parser.feed(b"3}");
+        parser.finish();
+        while let Ok(event) = parser.next_event() {
+            events.push(event.clone());
+            if event == Event::End {
+                break;
+            }
+        }
+        assert_eq!(
+            events,
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_string()),
+                Event::StartArray,
+                Event::Value(json_num!(1; uint)),
+                Event::Value(json_str!("xy\\\"")),
+                Event::EndArray,
+                Event::Key("b".to_string()),
+                Event::Value(json_num!(123; uint)),
+                Event::EndObject,
+                Event::End,
+            ]
+        );
+
+        // A scalar at the top level is only complete at the end of the input
+        let mut parser = PushParser::new();
+        parser.feed(b" 42");
+        assert_eq!(parser.value().unwrap(), None);
+        parser.finish();
+        assert_eq!(parser.value().unwrap(), Some(json_num!(42; uint)));
+
+        let mut parser = PushParser::new();
+        parser.feed(b"[\"abc");
+        assert_eq!(parser.value().unwrap(), None);
+        parser.finish();
+        let err = parser.value().unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnterminatedString));
+        assert_eq!((err.loc.line, err.loc.col), (1, 2));
+
+        let mut parser = PushParser::new();
+        parser.feed(b"[1,\n 2,]");
+        let err = parser.value().unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::InvalidTrailingComma));
+        assert_eq!((err.loc.line, err.loc.col), (2, 3));
+
+        // Long tokens in small chunks are scanned once, not again on every chunk
+        let long = "a".repeat(4 << 20);
+        let digits = "1".repeat(1 << 20);
+        for input in [format!("[\"{long}\"]"), format!("[0.{digits}]")] {
+            let start = std::time::Instant::now();
+            let mut parser = PushParser::new();
+            let mut val = None;
+            for chunk in input.as_bytes().chunks(1024) {
+                parser.feed(chunk);
+                val = val.or(parser.value().unwrap());
+            }
+            assert!(val.is_some());
+            assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        }
+
+        // The tokenizer no longer panics on cut off strings either
+        let err = parser::parse(tokenizer::Tokenizer::from_str("[\"abc")).unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnterminatedString));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_parser() {
+        use async_parser::{events, parse_async, ReadError};
+        use core::pin::Pin;
+        use futures_core::Stream;
+        use push::Event;
+        use tokio::io::AsyncWriteExt;
+
+        // Writes `input` in small chunks into a pipe, returning its other end
+        fn pipe(input: &'static str) -> tokio::io::DuplexStream {
+            let (mut writer, reader) = tokio::io::duplex(16);
+            tokio::spawn(async move {
+                for chunk in input.as_bytes().chunks(7) {
+                    writer.write_all(chunk).await.unwrap();
+                }
+            });
+            reader
+        }
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            for input in [
+                include_str!("../tests/wikipedia.json"),
+                include_str!("../tests/jsonplaceholder.json"),
+                include_str!("../tests/jsonorg.json"),
+                " 42 ",
+            ] {
+                let expected = parser::parse_value(tokenizer::Tokenizer::from_str(input)).unwrap();
+                assert_eq!(parse_async(pipe(input)).await.unwrap(), expected);
+            }
+            assert!(matches!(
+                parse_async(pipe("[1, 2] 3")).await,
+                Err(ReadError::Parsing(_))
+            ));
+            assert!(matches!(
+                parse_async(pipe("{\"a\": \"b")).await,
+                Err(ReadError::Parsing(_))
+            ));
+
+            let mut stream = events(pipe("{\"a\": [true, \"é\"]}"));
+            let mut events = Vec::new();
+            while let Some(event) =
+                core::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+            {
+                events.push(event.unwrap());
+            }
+            assert_eq!(
+                events,
+                vec![
+                    Event::StartObject,
+                    Event::Key("a".to_string()),
+                    Event::StartArray,
+                    Event::Value(JsonVal::Boolean(true)),
+                    Event::Value(json_str!("é")),
+                    Event::EndArray,
+                    Event::EndObject,
+                ]
+            );
+        });
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap() {
+        use std::borrow::Cow;
+        use tokenizer::ParsingErrorKind;
+
+        // SAFETY: none of the files used are changed while mapped
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/jsonorg.json");
+        let file = unsafe { from_file(path) }.unwrap();
+        let val = file.parse().unwrap();
+        let input = include_str!("../tests/jsonorg.json");
+        let expected = parser::parse(tokenizer::Tokenizer::from_str(input)).unwrap();
+        assert_eq!(JsonVal::from(val.clone()), expected);
+        let JsonValRef::Object(obj) = val else {
+            panic!("Expected an object");
+        };
+        let JsonValRef::Object(widget) = &obj["widget"] else {
+            panic!("Expected an object");
+        };
+        // Strings point into the map
+        let JsonValRef::String(Cow::Borrowed(debug)) = &widget["debug"] else {
+            panic!("Expected a borrowed string");
+        };
+        let map = file.as_bytes().as_ptr_range();
+        assert!(map.contains(&debug.as_ptr()));
+
+        let dir = std::env::temp_dir().join(format!("jsonparser-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.json");
+        std::fs::write(&path, b"[1, \"\xff\"]").unwrap();
+        let err = unsafe { from_file(&path) }.unwrap().parse().unwrap_err();
+        assert!(matches!(err.kind, ParsingErrorKind::UnsupportedToken));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(unsafe { from_file(dir.join("missing.json")) }.is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        use ndjson::NdjsonReader;
+
+        let records: Vec<_> = (0..1000)
+            .map(|i| {
+                format!("{{\"id\": {i}, \"name\": \"user \\\"{i}\\\"\", \"tags\": [\"a\", [{i}]]}}")
+            })
+            .collect();
+        let input = format!("[\n{},\n1, -1, 1.0, 1e2\n]", records.join(",\n"));
+        let parse = |input: &str| parser::parse(tokenizer::Tokenizer::from_str(input));
+        let JsonVal::Array(expected) = parse(&input).unwrap() else {
+            panic!("Expected an array");
+        };
+        // Numbers compare by value, so their variants are compared through
+        // `Debug`
+        let parsed = parallel::parse_array(input.as_bytes()).unwrap();
+        assert_eq!(format!("{parsed:?}"), format!("{expected:?}"));
+
+        let invalid = [
+            input.replace("\"id\": 500", "\"id\": 500,"),
+            input.replace("\"id\": 700", "\"id\" 700"),
+            input.replace("[\"a\", [3]]", "[\"a\" [3]]"),
+            format!("{input},"),
+            input.replace("\n]", ",\n]"),
+            "[1, {]}".to_string(),
+            "[[1}, 2]".to_string(),
+            "[1, \"abc]".to_string(),
+            "[1 2]".to_string(),
+            "[1, }]".to_string(),
+            "[1,,2]".to_string(),
+            "[\"\\x\"]".to_string(),
+            "[1]]".to_string(),
+            "[]x".to_string(),
+            "[1, 2".to_string(),
+            "".to_string(),
+        ];
+        for input in &invalid {
+            let expected = parse(input).map(|_| ()).unwrap_err();
+            let err = parallel::parse_array(input.as_bytes()).unwrap_err();
+            assert_eq!(format!("{err}"), format!("{expected}"), "{input}");
+        }
+        assert_eq!(parallel::parse_array(b" [ ] ").unwrap(), vec![]);
+        let err = parallel::parse_array(b"\n {\"a\": 1}").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            tokenizer::ParsingErrorKind::InvalidStartingToken
+        ));
+        assert_eq!((err.loc.line, err.loc.col), (2, 2));
+
+        let input = format!("{}\n\n{{\"bad\": }}\r\n  42\n\u{a0}7\n", records.join("\n"));
+        let expected: Vec<_> = NdjsonReader::new(input.as_bytes())
+            .map(|res| res.map_err(|err| err.to_string()))
+            .collect();
+        let results: Vec<_> = parallel::parse_ndjson(&input)
+            .into_iter()
+            .map(|res| res.map_err(|err| err.to_string()))
+            .collect();
+        assert_eq!(results, expected);
+        assert_eq!(results.len(), 1003);
+    }
+
+    #[test]
+    fn limits() {
+        use tokenizer::{ParsingError, ParsingErrorKind, Tokenizer};
+
+        let parse = |input: &str, limits: Limits| {
+            let options = Options {
+                limits,
+                ..Default::default()
+            };
+            parser::parse_value(Tokenizer::from_str(input).with_options(options))
+        };
+        let input = "{\"a\": [1, 2, {\"b\": \"xyz\"}], \"c\": -12.5}";
+
+        // Exactly at every limit is fine
+        let limits = Limits {
+            max_depth: Some(3),
+            max_input_size: Some(input.len()),
+            max_string_length: Some(3),
+            max_members: Some(2),
+            max_elements: Some(3),
+            max_number_length: Some(5),
+            max_nodes: Some(7),
+        };
+        assert!(parse(input, limits).is_ok());
+
+        let check = |limits: Limits, expected: ParsingErrorKind| {
+            let err = parse(input, limits).expect_err("Expected an error");
+            assert_eq!(err.kind(), expected);
+        };
+        check(
+            Limits {
+                max_depth: Some(2),
+                ..limits
+            },
+            ParsingErrorKind::DepthLimitExceeded,
+        );
+        check(
+            Limits {
+                max_input_size: Some(input.len() - 1),
+                ..limits
+            },
+            ParsingErrorKind::InputTooLarge,
+        );
+        check(
+            Limits {
+                max_string_length: Some(2),
+                ..limits
+            },
+            ParsingErrorKind::StringTooLong,
+        );
+        check(
+            Limits {
+                max_members: Some(1),
+                ..limits
+            },
+            ParsingErrorKind::TooManyMembers,
+        );
+        check(
+            Limits {
+                max_elements: Some(2),
+                ..limits
+            },
+            ParsingErrorKind::TooManyElements,
+        );
+        check(
+            Limits {
+                max_number_length: Some(4),
+                ..limits
+            },
+            ParsingErrorKind::NumberTooLong,
+        );
+        check(
+            Limits {
+                max_nodes: Some(6),
+                ..limits
+            },
+            ParsingErrorKind::TooManyNodes,
+        );
+
+        // Every parser checks every limit, failing where the tokenizer does
+        let all = |input: &str, limits: Limits| {
+            let outcome = |res: Result<(), ParsingError>| res.map_err(|err| err.to_string());
+            let mut res = vec![
+                (
+                    "bytes",
+                    outcome(bytes::parse_with_limits(input.as_bytes(), limits).map(|_| ())),
+                ),
+                (
+                    "borrowed",
+                    outcome(borrowed::parse_with_limits(input, limits).map(|_| ())),
+                ),
+                (
+                    "tape",
+                    outcome(
+                        tape::Document::parse_with_limits(input.as_bytes(), limits).map(|_| ()),
+                    ),
+                ),
+                (
+                    "ondemand",
+                    outcome(
+                        ondemand::Document::from_str(input)
+                            .with_limits(limits)
+                            .root()
+                            .to_json_val()
+                            .map(|_| ()),
+                    ),
+                ),
+            ];
+            #[cfg(feature = "simd")]
+            res.extend([
+                (
+                    "simd",
+                    outcome(simd::parse_with_limits(input.as_bytes(), limits).map(|_| ())),
+                ),
+                (
+                    "simd tape",
+                    outcome(simd::parse_tape_with_limits(input.as_bytes(), limits).map(|_| ())),
+                ),
+            ]);
+            #[cfg(feature = "rayon")]
+            if input.starts_with('[') {
+                res.push((
+                    "parallel",
+                    outcome(
+                        parallel::parse_array_with_limits(input.as_bytes(), limits).map(|_| ()),
+                    ),
+                ));
+            }
+            for size in [1, 7, input.len()] {
+                let mut parser = push::PushParser::new().with_limits(limits);
+                let mut run = || {
+                    for chunk in input.as_bytes().chunks(size) {
+                        parser.feed(chunk);
+                        parser.value()?;
+                    }
+                    parser.finish();
+                    parser.value().map(|_| ())
+                };
+                res.push(("push", outcome(run())));
+            }
+            #[cfg(feature = "async")]
+            {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .unwrap();
+                let res_async = rt.block_on(async_parser::parse_async_with_limits(
+                    input.as_bytes(),
+                    limits,
+                ));
+                res.push((
+                    "async",
+                    res_async.map(|_| ()).map_err(|err| err.to_string()),
+                ));
+            }
+            res
+        };
+        for input in [input, "[{\"b\": \"xyz\"}, [1, -12.5], \"ab\"]"] {
+            for limits in [
+                limits,
+                Limits {
+                    max_depth: Some(1),
+                    ..limits
+                },
+                Limits {
+                    max_input_size: Some(20),
+                    ..limits
+                },
+                Limits {
+                    max_string_length: Some(2),
+                    ..limits
+                },
+                Limits {
+                    max_members: Some(0),
+                    ..limits
+                },
+                Limits {
+                    max_elements: Some(1),
+                    ..limits
+                },
+                Limits {
+                    max_number_length: Some(4),
+                    ..limits
+                },
+                Limits {
+                    max_nodes: Some(3),
+                    ..limits
+                },
+                Limits {
+                    max_nodes: Some(0),
+                    ..limits
+                },
+            ] {
+                let expected = parse(input, limits)
+                    .map(|_| ())
+                    .map_err(|err| err.to_string());
+                for (name, res) in all(input, limits) {
+                    assert_eq!(res, expected, "{} for {} with {:?}", name, input, limits);
+                }
+            }
+        }
+        // Up to where a limit is reached, so a huge string or number isn't
+        // buffered before failing
+        let limits = Limits {
+            max_string_length: Some(10),
+            max_number_length: Some(10),
+            ..Default::default()
+        };
+        for input in ["[\"", "[-"] {
+            let mut parser = push::PushParser::new().with_limits(limits);
+            parser.feed(input.as_bytes());
+            parser.feed(&[b'1'; 11]);
+            assert!(parser.value().is_err());
+        }
+        let lines = "[1, 2]\n[1, 2, 3]\n";
+        let options = Options {
+            limits: Limits {
+                max_elements: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut reader = ndjson::NdjsonReader::new(lines.as_bytes()).with_options(options);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        #[cfg(feature = "rayon")]
+        {
+            let res = parallel::parse_ndjson_with_options(lines, options);
+            assert!(res[0].is_ok() && res[1].is_err());
+        }
+
+        // Deep nesting fails early instead of overflowing the stack
+        let deep = "[".repeat(100_000);
+        let limits = Limits {
+            max_depth: Some(64),
+            ..Default::default()
+        };
+        let err = parse(&deep, limits).expect_err("Expected an error");
+        assert_eq!(err.kind(), ParsingErrorKind::DepthLimitExceeded);
+        assert_eq!((err.loc().line(), err.loc().col()), (1, 65));
+
+        // Same for the parsers working on bytes
+        let deep = "[".repeat(1_000_000);
+        let check = |res: Result<(), ParsingError>| {
+            let err = res.expect_err("Expected an error");
+            assert_eq!(err.kind(), ParsingErrorKind::DepthLimitExceeded);
+            assert_eq!((err.loc().line(), err.loc().col()), (1, 65));
+        };
+        check(bytes::parse_with_limits(deep.as_bytes(), limits).map(|_| ()));
+        check(borrowed::parse_with_limits(&deep, limits).map(|_| ()));
+        check(tape::Document::parse_with_limits(deep.as_bytes(), limits).map(|_| ()));
+        #[cfg(feature = "simd")]
+        check(simd::parse_with_limits(deep.as_bytes(), limits).map(|_| ()));
+        #[cfg(feature = "rayon")]
+        check(parallel::parse_array_with_limits(deep.as_bytes(), limits).map(|_| ()));
+        let doc = ondemand::Document::from_str(&deep).with_limits(limits);
+        check(doc.root().to_json_val().map(|_| ()));
+        // Right at the limit is fine
+        let nested = format!("{}{}", "[".repeat(64), "]".repeat(64));
+        assert!(bytes::parse_with_limits(nested.as_bytes(), limits).is_ok());
+        assert!(borrowed::parse_with_limits(&nested, limits).is_ok());
+        assert!(tape::Document::parse_with_limits(nested.as_bytes(), limits).is_ok());
+        #[cfg(feature = "simd")]
+        assert!(simd::parse_with_limits(nested.as_bytes(), limits).is_ok());
+        #[cfg(feature = "rayon")]
+        assert!(parallel::parse_array_with_limits(nested.as_bytes(), limits).is_ok());
+
+        // The nesting is limited by default
+        let col = tokenizer::DEFAULT_MAX_DEPTH + 1;
+        let check = |res: Result<(), ParsingError>| {
+            let err = res.expect_err("Expected an error");
+            assert_eq!(err.kind(), ParsingErrorKind::DepthLimitExceeded);
+            assert_eq!((err.loc().line(), err.loc().col()), (1, col));
+        };
+        check(parser::parse(Tokenizer::from_str(&deep)).map(|_| ()));
+        check(parser::parse_value(Tokenizer::from_str(&deep)).map(|_| ()));
+        check(bytes::parse(deep.as_bytes()).map(|_| ()));
+        check(borrowed::parse(&deep).map(|_| ()));
+        check(tape::Document::parse(deep.as_bytes()).map(|_| ()));
+        #[cfg(feature = "simd")]
+        check(simd::parse(deep.as_bytes()).map(|_| ()));
+        #[cfg(feature = "rayon")]
+        check(parallel::parse_array(deep.as_bytes()).map(|_| ()));
+        check(
+            ondemand::Document::from_str(&deep)
+                .root()
+                .to_json_val()
+                .map(|_| ()),
+        );
+        let mut parser = push::PushParser::new();
+        parser.feed(deep.as_bytes());
+        check(parser.value().map(|_| ()));
+    }
+
+    #[test]
+    fn no_panics() {
+        use tokenizer::{ParsingErrorKind, Tokenizer};
+
+        let json5 = Options {
+            json5: true,
+            ..Default::default()
+        };
+        for input in [
+            "", "\"", "[\"", "{\"a", "{\"a\":", "['", "[1e", "[-]", "[\"\\",
+        ] {
+            for options in [Options::default(), json5] {
+                assert!(parser::parse(Tokenizer::from_str(input).with_options(options)).is_err());
+            }
+        }
+
+        let err =
+            parser::parse_value(Tokenizer::from_str("[\"abc")).expect_err("Expected an error");
+        assert!(matches!(err.kind, ParsingErrorKind::UnterminatedString));
+
+        // Only identifiers are accepted
+        let mut tokenizer = Tokenizer::from_str("1");
+        let token = tokenizer.next_token().expect("Expected a token");
+        let err = parser::parse_ident(token, &mut tokenizer).expect_err("Expected an error");
+        assert!(matches!(err.kind, ParsingErrorKind::UnexpectedToken));
+    }
 }
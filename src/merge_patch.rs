@@ -0,0 +1,60 @@
+use crate::parser::{JsonVal, MapType};
+
+// JSON Merge Patch (RFC 7386)
+
+impl JsonVal {
+    // Objects in `patch` are merged recursively, members set to `null` are
+    // removed and any other value replaces the one in `self`
+    pub fn merge_patch(&mut self, patch: &JsonVal) {
+        let JsonVal::Object(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if !matches!(self, JsonVal::Object(_)) {
+            *self = JsonVal::Object(MapType::new());
+        }
+        if let JsonVal::Object(target) = self {
+            for (key, val) in patch {
+                if *val == JsonVal::Null {
+                    target.shift_remove(key);
+                } else {
+                    target
+                        .entry(key.clone())
+                        .or_insert(JsonVal::Null)
+                        .merge_patch(val);
+                }
+            }
+        }
+    }
+}
+
+// Computes a merge patch that turns `from` into `to`. Merge patches use `null`
+// to remove members, so members of `to` that are `null` can't be represented
+// and end up removed instead.
+pub fn diff(from: &JsonVal, to: &JsonVal) -> JsonVal {
+    match (from, to) {
+        (JsonVal::Object(from), JsonVal::Object(to)) => {
+            let mut patch = MapType::new();
+            for key in from.keys() {
+                if !to.contains_key(key) {
+                    patch.insert(key.clone(), JsonVal::Null);
+                }
+            }
+            for (key, to_val) in to {
+                match from.get(key) {
+                    Some(from_val) if from_val == to_val => {}
+                    Some(from_val) => {
+                        patch.insert(key.clone(), diff(from_val, to_val));
+                    }
+                    None => {
+                        patch.insert(key.clone(), to_val.clone());
+                    }
+                }
+            }
+            JsonVal::Object(patch)
+        }
+        // An object replacing something else still gets merged into an empty
+        // object, which drops its `null` members
+        _ => to.clone(),
+    }
+}
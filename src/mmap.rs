@@ -0,0 +1,61 @@
+use crate::borrowed::{self, JsonValRef};
+use crate::bytes::to_str;
+use crate::tokenizer::{Limits, ParsingError};
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+
+// Memory-mapped files, so big files can be parsed without reading them into a
+// `String` first
+
+pub struct MappedFile {
+    map: Mmap,
+}
+
+/// Maps the file at `path`. Parse it with `MappedFile::parse`, which gives
+/// values borrowing their strings from the map:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // SAFETY: nothing changes dump.json while it is mapped
+/// let file = unsafe { jsonparser::from_file("dump.json")? };
+/// let val = file.parse()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// To parse a file that could change, read it with `std::fs::read` and use
+/// `borrowed::parse` instead.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated, by this or any other process,
+/// while the `MappedFile` or anything borrowed from it is alive. Otherwise the
+/// `&str` and values handed out can change under the program, or the reads
+/// can fault.
+pub unsafe fn from_file(path: impl AsRef<Path>) -> std::io::Result<MappedFile> {
+    let file = File::open(path)?;
+    // SAFETY: the caller guarantees the file isn't changed while mapped
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(MappedFile { map })
+}
+
+impl MappedFile {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn as_str(&self) -> Result<&str, ParsingError> {
+        to_str(&self.map)
+    }
+
+    // Parses the file with `borrowed::parse`, only copying strings with escapes
+    pub fn parse(&self) -> Result<JsonValRef<'_>, ParsingError> {
+        borrowed::parse(self.as_str()?)
+    }
+
+    // Parses the file with `borrowed::parse_with_limits`, for untrusted files
+    pub fn parse_with_limits(&self, limits: Limits) -> Result<JsonValRef<'_>, ParsingError> {
+        borrowed::parse_with_limits(self.as_str()?, limits)
+    }
+}
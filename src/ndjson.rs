@@ -0,0 +1,194 @@
+use crate::parser::{parse_value, JsonVal};
+use crate::tokenizer::{Options, ParsingError, Tokenizer};
+use core::fmt::Display;
+use std::io::{BufRead, Write};
+
+// Newline-delimited JSON (NDJSON, also known as JSON Lines), one value per line
+
+#[derive(Debug)]
+pub enum NdjsonErrorKind {
+    Io(std::io::Error),
+    Parsing(ParsingError),
+    // Longer than `NdjsonReader::with_max_line_length` allows
+    LineTooLong,
+}
+
+#[derive(Debug)]
+pub struct NdjsonError {
+    pub(crate) kind: NdjsonErrorKind,
+    // Starting at 1
+    pub(crate) line: usize,
+}
+
+impl NdjsonError {
+    pub fn kind(&self) -> &NdjsonErrorKind {
+        &self.kind
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Display for NdjsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            NdjsonErrorKind::Io(err) => write!(f, "line {}: {}", self.line, err),
+            NdjsonErrorKind::Parsing(err) => write!(f, "line {}: {}", self.line, err),
+            NdjsonErrorKind::LineTooLong => write!(f, "line {}: too long", self.line),
+        }
+    }
+}
+
+impl std::error::Error for NdjsonError {}
+
+// Yields every value in the input. A line that fails to parse gives an error,
+// but reading can continue with the next line. Blank lines are skipped.
+pub struct NdjsonReader<R: BufRead> {
+    reader: R,
+    line: usize,
+    buf: Vec<u8>,
+    done: bool,
+    options: Options,
+    max_line_length: Option<usize>,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            buf: Vec::new(),
+            done: false,
+            options: Options::default(),
+            max_line_length: None,
+        }
+    }
+
+    // Options for parsing every line
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    // In bytes, not counting the `\n`. Longer lines aren't kept in memory but
+    // skipped, giving an error.
+    pub fn with_max_line_length(mut self, max: usize) -> Self {
+        self.max_line_length = Some(max);
+        self
+    }
+
+    // The line of the last value or error returned
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // Reads the next line into `buf` without its `\n`, keeping at most one
+    // byte more than `max_line_length`. Returns the length of the whole line,
+    // or `None` at the end of the input.
+    fn read_line(&mut self) -> std::io::Result<Option<usize>> {
+        let limit = self
+            .max_line_length
+            .map_or(usize::MAX, |max| max.saturating_add(1));
+        let mut len = None;
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if available.is_empty() {
+                return Ok(len);
+            }
+            let newline = available.iter().position(|&b| b == b'\n');
+            let end = newline.unwrap_or(available.len());
+            let keep = end.min(limit.saturating_sub(self.buf.len()));
+            self.buf.extend_from_slice(&available[..keep]);
+            len = Some(len.unwrap_or(0) + end);
+            self.reader.consume(newline.map_or(end, |i| i + 1));
+            if newline.is_some() {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<JsonVal, NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            self.line += 1;
+            // Read as bytes, so a line that isn't UTF-8 is an error of its own
+            // and the next lines can still be read
+            match self.read_line() {
+                Ok(None) => self.done = true,
+                Ok(Some(len)) if self.max_line_length.is_some_and(|max| len > max) => {
+                    return Some(Err(NdjsonError {
+                        kind: NdjsonErrorKind::LineTooLong,
+                        line: self.line,
+                    }));
+                }
+                Ok(Some(_)) => {
+                    let text = match core::str::from_utf8(&self.buf) {
+                        Ok(text) => trim_line(text),
+                        Err(err) => {
+                            return Some(Err(NdjsonError {
+                                kind: NdjsonErrorKind::Io(std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    err,
+                                )),
+                                line: self.line,
+                            }));
+                        }
+                    };
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let tokenizer = Tokenizer::from_str(text).with_options(self.options);
+                    return Some(parse_value(tokenizer).map_err(|err| NdjsonError {
+                        kind: NdjsonErrorKind::Parsing(err),
+                        line: self.line,
+                    }));
+                }
+                Err(err) => {
+                    // The reader can't be trusted to make progress after an error
+                    self.done = true;
+                    return Some(Err(NdjsonError {
+                        kind: NdjsonErrorKind::Io(err),
+                        line: self.line,
+                    }));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Only JSON's whitespace, as `parse_value` rejects any other
+pub(crate) fn trim_line(line: &str) -> &str {
+    line.trim_matches([' ', '\t', '\n', '\r'])
+}
+
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(&mut self, val: &JsonVal) -> std::io::Result<()> {
+        writeln!(self.writer, "{}", val.to_compact_string())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
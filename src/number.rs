@@ -0,0 +1,317 @@
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone)]
+pub enum Number {
+    UnsignedInt(u64),
+    SignedInt(i64),
+    Float(f64),
+    // The original text of the number, only produced when parsing with
+    // `Options::arbitrary_precision`
+    Raw(String),
+}
+
+// Numbers are compared by value, no matter the variant, so `UnsignedInt(1)`,
+// `SignedInt(1)`, `Float(1.0)` and `Raw("1e0")` are all equal. Floats compare
+// as the decimal they are printed as, so `Float(0.1) == Raw("0.1")`. To make
+// this a total order `0.0 == -0.0`, and `NaN` is equal to itself and greater
+// than everything else.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use Number::*;
+        match (self, other) {
+            (UnsignedInt(a), UnsignedInt(b)) => a.cmp(b),
+            (SignedInt(a), SignedInt(b)) => a.cmp(b),
+            (UnsignedInt(a), SignedInt(b)) => (*a as i128).cmp(&(*b as i128)),
+            (SignedInt(a), UnsignedInt(b)) => (*a as i128).cmp(&(*b as i128)),
+            (Float(a), Float(b)) if !a.is_nan() && !b.is_nan() => {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            _ => self.canonical().cmp(&other.canonical()),
+        }
+    }
+}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().hash(state);
+    }
+}
+
+// The value of a number, normalized so that equal numbers have equal
+// representations. The variant order is the order of the values.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Canonical {
+    NegInfinity,
+    Finite(Decimal),
+    Infinity,
+    NaN,
+}
+
+// `0.digits * 10^exp`, without leading or trailing zeros in `digits`. Zero has
+// no digits.
+#[derive(PartialEq, Eq, Hash)]
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exp: i64,
+}
+
+impl Decimal {
+    // Parses any number accepted by `is_valid_number`, or the output of `Debug`
+    // for finite floats
+    fn parse(s: &str) -> Self {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, "0"),
+        };
+        let exp = exp.trim_start_matches('+');
+        let exp: i64 = exp.parse().unwrap_or(if exp.starts_with('-') {
+            i64::MIN / 2
+        } else {
+            i64::MAX / 2
+        });
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let mut digits: Vec<u8> = int.bytes().chain(frac.bytes()).collect();
+        let mut exp = exp.saturating_add(int.len() as i64);
+        let leading = digits.iter().take_while(|&&d| d == b'0').count();
+        digits.drain(..leading);
+        exp = exp.saturating_sub(leading as i64);
+        while digits.last() == Some(&b'0') {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            Self {
+                negative: false,
+                digits,
+                exp: 0,
+            }
+        } else {
+            Self {
+                negative,
+                digits,
+                exp,
+            }
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // -1 for negatives, 0 for zero, 1 for positives
+        let sign = |d: &Decimal| match (d.negative, d.digits.is_empty()) {
+            (_, true) => 0,
+            (true, false) => -1,
+            (false, false) => 1,
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal => {}
+            ord => return ord,
+        }
+        let magnitude = self
+            .exp
+            .cmp(&other.exp)
+            .then_with(|| self.digits.cmp(&other.digits));
+        if self.negative {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl Number {
+    fn canonical(&self) -> Canonical {
+        match self {
+            Number::UnsignedInt(n) => Canonical::Finite(Decimal::parse(&n.to_string())),
+            Number::SignedInt(n) => Canonical::Finite(Decimal::parse(&n.to_string())),
+            Number::Float(n) if n.is_nan() => Canonical::NaN,
+            Number::Float(n) if *n == f64::INFINITY => Canonical::Infinity,
+            Number::Float(n) if *n == f64::NEG_INFINITY => Canonical::NegInfinity,
+            Number::Float(n) => Canonical::Finite(Decimal::parse(&format!("{:?}", n))),
+            Number::Raw(s) => Canonical::Finite(Decimal::parse(s)),
+        }
+    }
+}
+
+// What to do with floats that can't be represented in JSON (`NaN` and the
+// infinities) when serializing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinite {
+    #[default]
+    Null,
+    Error,
+}
+
+// The float that couldn't be serialized with `NonFinite::Error`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonFiniteError {
+    pub(crate) value: f64,
+}
+
+impl NonFiniteError {
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl core::fmt::Display for NonFiniteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} can't be written as JSON", self.value)
+    }
+}
+
+impl std::error::Error for NonFiniteError {}
+
+impl core::fmt::Display for Number {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Number::UnsignedInt(n) => write!(f, "{}", n),
+            Number::SignedInt(n) => write!(f, "{}", n),
+            Number::Float(n) => {
+                if n.is_finite() {
+                    // `Debug` for floats gives the shortest text that parses back to
+                    // the same value, and always keeps a `.` or an exponent, so it
+                    // doesn't come back as an integer. `-0.0` is kept as is.
+                    write!(f, "{:?}", n)
+                } else {
+                    write!(f, "null")
+                }
+            }
+            Number::Raw(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Number {
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Number::UnsignedInt(n) => Some(*n as u128),
+            Number::SignedInt(n) => u128::try_from(*n).ok(),
+            Number::Float(_) => None,
+            Number::Raw(s) => s.parse().ok(),
+        }
+    }
+
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Number::UnsignedInt(n) => Some(*n as i128),
+            Number::SignedInt(n) => Some(*n as i128),
+            Number::Float(_) => None,
+            Number::Raw(s) => s.parse().ok(),
+        }
+    }
+
+    // Can lose precision, as the closest `f64` to the number is returned
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::UnsignedInt(n) => *n as f64,
+            Number::SignedInt(n) => *n as f64,
+            Number::Float(n) => *n,
+            // Raw numbers are always validated, so this can't fail
+            Number::Raw(s) => s.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    pub fn as_big_decimal(&self) -> Option<bigdecimal::BigDecimal> {
+        use core::str::FromStr;
+        match self {
+            Number::UnsignedInt(n) => Some((*n).into()),
+            Number::SignedInt(n) => Some((*n).into()),
+            Number::Float(n) => bigdecimal::BigDecimal::try_from(*n).ok(),
+            Number::Raw(s) => bigdecimal::BigDecimal::from_str(s).ok(),
+        }
+    }
+}
+
+// Parses a JSON number as an `f64`, rejecting anything that isn't valid JSON.
+// Numbers too big for an `f64`, like `1e400`, become infinite.
+pub(crate) fn parse_f64(s: &str) -> Option<f64> {
+    if !is_valid_number(s) {
+        return None;
+    }
+    // core's parser is correctly rounded, so any text written by `Display`
+    // comes back as the exact same value
+    s.parse().ok()
+}
+
+// Parses a JSON number into the smallest fitting variant, same as the parser
+// does: integers as `UnsignedInt` or `SignedInt`, anything else as `Float`
+pub(crate) fn parse_number(s: &str) -> Option<Number> {
+    if s.contains(['.', 'e', 'E']) {
+        parse_f64(s).map(Number::Float)
+    } else if !is_valid_number(s) {
+        None
+    } else if s.starts_with('-') {
+        s.parse().ok().map(Number::SignedInt)
+    } else {
+        s.parse().ok().map(Number::UnsignedInt)
+    }
+}
+
+// Checks `s` against the number grammar of RFC 8259:
+// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+pub(crate) fn is_valid_number(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            digits(&mut i);
+        }
+        _ => return false,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        i += 1;
+        if let Some(b'+' | b'-') = bytes.get(i) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
@@ -0,0 +1,284 @@
+use crate::bytes::{check_input_size, loc_at, Lexer, Parser};
+use crate::parser::JsonVal;
+use crate::tokenizer::{Limits, Loc, ParsingError, ParsingErrorKind};
+use core::fmt::Display;
+
+// On-demand navigation over the raw text of a document. Nothing is parsed up
+// front: every step scans forward from the current value, checking and
+// skipping the parts that aren't needed without building any `JsonVal`. Only
+// the text that was scanned is validated, so errors further on go unnoticed.
+// A key or index that isn't there is an error of its own kind, so steps can be
+// chained with `?` and still be told apart from malformed input. As scanning a
+// value recurses into it, the default `Limits` apply unless `with_limits` is
+// used.
+//
+//     let doc = Document::from_str(input);
+//     let email = doc.get("users")?.at(3)?.get("email")?.as_str()?;
+#[derive(Debug, Clone, Copy)]
+pub struct Document<'a> {
+    input: &'a str,
+    limits: Limits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OndemandErrorKind {
+    // The text that was scanned isn't valid JSON
+    Parsing(ParsingErrorKind),
+    KeyNotFound,
+    IndexOutOfBounds,
+}
+
+#[derive(Debug, Clone)]
+pub struct OndemandError {
+    pub(crate) kind: OndemandErrorKind,
+    // Where the text is malformed, or else the start of the object or array
+    pub(crate) loc: Loc,
+}
+
+impl OndemandError {
+    pub fn kind(&self) -> OndemandErrorKind {
+        self.kind
+    }
+
+    pub fn loc(&self) -> &Loc {
+        &self.loc
+    }
+}
+
+impl From<ParsingError> for OndemandError {
+    fn from(err: ParsingError) -> Self {
+        Self {
+            kind: OndemandErrorKind::Parsing(err.kind),
+            loc: err.loc,
+        }
+    }
+}
+
+impl Display for OndemandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} at line {}, column {}",
+            self.kind, self.loc.line, self.loc.col
+        )
+    }
+}
+
+impl std::error::Error for OndemandError {}
+
+impl<'a> Document<'a> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Self {
+            input,
+            limits: Limits::default(),
+        }
+    }
+
+    // Only what is scanned is counted, from the value being scanned rather
+    // than from the root, so `max_depth` and `max_nodes` apply to that value
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn root(&self) -> Value<'a> {
+        let mut parser = Parser::new(self.input);
+        parser.peek_val();
+        Value {
+            input: self.input,
+            pos: parser.pos(),
+            limits: self.limits,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Value<'a>, OndemandError> {
+        self.root().get(key)
+    }
+
+    pub fn get_first(&self, key: &str) -> Result<Value<'a>, OndemandError> {
+        self.root().get_first(key)
+    }
+
+    pub fn at(&self, i: usize) -> Result<Value<'a>, OndemandError> {
+        self.root().at(i)
+    }
+
+    pub fn elements(&self) -> Elements<'a> {
+        self.root().elements()
+    }
+}
+
+// A value in a `Document`, which hasn't been checked yet
+#[derive(Debug, Clone, Copy)]
+pub struct Value<'a> {
+    input: &'a str,
+    // Offset of the first byte of the value
+    pos: usize,
+    limits: Limits,
+}
+
+impl<'a> Value<'a> {
+    fn parser(&self) -> Parser<'a> {
+        let mut parser = Parser::new(self.input).with_limits(self.limits);
+        parser.seek(self.pos);
+        parser
+    }
+
+    // Checks the size of the whole input before scanning any of it
+    fn scan(&self) -> Result<Parser<'a>, ParsingError> {
+        check_input_size(self.input.as_bytes(), self.limits)?;
+        Ok(self.parser())
+    }
+
+    fn value_at(&self, parser: &mut Parser<'a>) -> Value<'a> {
+        parser.peek_val();
+        Value {
+            input: self.input,
+            pos: parser.pos(),
+            limits: self.limits,
+        }
+    }
+
+    // Opens the container, failing if the value is something else
+    fn open(&self, parser: &mut Parser<'a>, open: u8) -> Result<(), ParsingError> {
+        if parser.peek_val() != Some(open) {
+            return Err(parser.error(ParsingErrorKind::UnexpectedToken, self.pos));
+        }
+        parser.bump();
+        Ok(())
+    }
+
+    fn not_found(&self, kind: OndemandErrorKind) -> OndemandError {
+        OndemandError {
+            kind,
+            loc: loc_at(self.input.as_bytes(), self.pos),
+        }
+    }
+
+    // Calls `found` with the value of every member named `key`, until it
+    // returns true
+    fn find(
+        &self,
+        key: &str,
+        mut found: impl FnMut(Value<'a>) -> bool,
+    ) -> Result<(), ParsingError> {
+        let mut parser = self.scan()?;
+        self.open(&mut parser, b'{')?;
+        if parser.is_empty(b'}') {
+            return Ok(());
+        }
+        let mut len = 0;
+        loop {
+            len += 1;
+            parser.check_len(len, b'}')?;
+            if parser.parse_ident()? == key && found(self.value_at(&mut parser)) {
+                return Ok(());
+            }
+            parser.skip_val()?;
+            if parser.parse_separator(b'}')? {
+                return Ok(());
+            }
+        }
+    }
+
+    // Value of `key` in an object. Keys are compared as written, without
+    // decoding their escapes. With duplicate keys the last one is used, like
+    // when converting to `JsonVal`, so the whole object is scanned. Use
+    // `get_first` to stop at the first match instead.
+    pub fn get(&self, key: &str) -> Result<Value<'a>, OndemandError> {
+        let mut last = None;
+        self.find(key, |val| {
+            last = Some(val);
+            false
+        })?;
+        last.ok_or_else(|| self.not_found(OndemandErrorKind::KeyNotFound))
+    }
+
+    // Like `get`, but with duplicate keys the first one is used, so only the
+    // object up to it is scanned
+    pub fn get_first(&self, key: &str) -> Result<Value<'a>, OndemandError> {
+        let mut first = None;
+        self.find(key, |val| first.replace(val).is_none())?;
+        first.ok_or_else(|| self.not_found(OndemandErrorKind::KeyNotFound))
+    }
+
+    // Element `i` of an array. Use `elements` to go through all of them, as
+    // every call scans the array from its start.
+    pub fn at(&self, i: usize) -> Result<Value<'a>, OndemandError> {
+        match self.elements().nth(i) {
+            Some(res) => Ok(res?),
+            None => Err(self.not_found(OndemandErrorKind::IndexOutOfBounds)),
+        }
+    }
+
+    // The elements of an array, scanning each one only once. It ends after
+    // the first error.
+    pub fn elements(&self) -> Elements<'a> {
+        Elements {
+            array: *self,
+            parser: self.parser(),
+            len: 0,
+            started: false,
+            done: false,
+        }
+    }
+
+    // Contents of a string, with its escapes
+    pub fn as_str(&self) -> Result<&'a str, ParsingError> {
+        let mut parser = self.scan()?;
+        if parser.peek_val() != Some(b'"') {
+            return Err(parser.error(ParsingErrorKind::UnexpectedToken, self.pos));
+        }
+        parser.parse_string_val()
+    }
+
+    // Parses the whole value
+    pub fn to_json_val(&self) -> Result<JsonVal, ParsingError> {
+        self.scan()?.parse_val()
+    }
+}
+
+pub struct Elements<'a> {
+    array: Value<'a>,
+    parser: Parser<'a>,
+    // Elements given so far
+    len: usize,
+    // Whether the array was opened, so the parser is on the last element given
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Elements<'a> {
+    fn step(&mut self) -> Result<Option<Value<'a>>, ParsingError> {
+        if !self.started {
+            self.started = true;
+            check_input_size(self.array.input.as_bytes(), self.array.limits)?;
+            self.array.open(&mut self.parser, b'[')?;
+            if self.parser.is_empty(b']') {
+                return Ok(None);
+            }
+        } else {
+            self.parser.skip_val()?;
+            if self.parser.parse_separator(b']')? {
+                return Ok(None);
+            }
+        }
+        self.len += 1;
+        self.parser.check_len(self.len, b']')?;
+        Ok(Some(self.array.value_at(&mut self.parser)))
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<Value<'a>, ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.step().transpose();
+        self.done = !matches!(res, Some(Ok(_)));
+        res
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bytes::{self, to_str, Lexer, Parser};
+use crate::ndjson::{trim_line, NdjsonError, NdjsonErrorKind};
+use crate::parser::{parse, parse_value, JsonVal};
+use crate::tokenizer::{Limits, Options, ParsingError, ParsingErrorKind, Tokenizer};
+use rayon::prelude::*;
+
+// Parallel parsing of big arrays and NDJSON, with the `rayon` feature. The
+// results, including errors and their locations, are the same as parsing
+// sequentially with `parser::parse`.
+
+// Parses a top level array, like `parser::parse`, splitting it into its
+// elements first so they can be parsed concurrently
+pub fn parse_array(input: &[u8]) -> Result<Vec<JsonVal>, ParsingError> {
+    parse_array_with_limits(input, Limits::default())
+}
+
+// The elements are parsed with `bytes`, which accepts the same input as
+// `parser::parse` but reports errors with its own kinds and locations. So on
+// any error the input is parsed again with `parser::parse`, for its error,
+// which is also the case when the elements together go over `limits`.
+pub fn parse_array_with_limits(input: &[u8], limits: Limits) -> Result<Vec<JsonVal>, ParsingError> {
+    let text = to_str(input)?;
+    let sequential = || {
+        let options = Options {
+            limits,
+            ..Default::default()
+        };
+        match parse(Tokenizer::from_str(text).with_options(options))? {
+            JsonVal::Array(arr) => Ok(arr),
+            _ => Err(ParsingError {
+                kind: ParsingErrorKind::InvalidStartingToken,
+                loc: bytes::loc_at(input, first_byte(input)),
+            }),
+        }
+    };
+    // A depth or node count of 0 doesn't even allow the array
+    let bounds = element_bounds(input).filter(|bounds| {
+        let within = |limit: Option<usize>, len| limit.is_none_or(|max| len <= max);
+        within(limits.max_depth, 1)
+            && within(limits.max_nodes, 1)
+            && within(limits.max_elements, bounds.len())
+            && within(limits.max_input_size, input.len())
+    });
+    let Some(bounds) = bounds else {
+        // Anything unusual is left for the sequential parser to report
+        return sequential();
+    };
+    // The elements are one level down from the array, and each one can use
+    // all the nodes left, which are checked again once they are all counted
+    let element_limits = Limits {
+        max_depth: limits.max_depth.map(|max| max - 1),
+        max_nodes: limits.max_nodes.map(|max| max - 1),
+        ..limits
+    };
+    let results: Vec<_> = bounds
+        .par_iter()
+        .map(|&(start, end)| {
+            let mut parser = Parser::new(text).with_limits(element_limits);
+            parser.seek(start);
+            let val = parser.parse_val()?;
+            parser.peek_val();
+            if parser.pos() != end {
+                return Err(parser.error(ParsingErrorKind::MissingEndingComma, parser.pos()));
+            }
+            Ok((val, parser.nodes()))
+        })
+        .collect();
+    let Ok(results) = results.into_iter().collect::<Result<Vec<_>, _>>() else {
+        return sequential();
+    };
+    let nodes = 1 + results.iter().map(|(_, nodes)| nodes).sum::<usize>();
+    if limits.max_nodes.is_some_and(|max| nodes > max) {
+        return sequential();
+    }
+    Ok(results.into_iter().map(|(val, _)| val).collect())
+}
+
+fn first_byte(input: &[u8]) -> usize {
+    input
+        .iter()
+        .position(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+        .unwrap_or(input.len())
+}
+
+// Finds where each element of the top level array starts and where its comma
+// or the closing bracket is. Returns `None` if the input isn't a well formed
+// array, including empty elements like the one after a trailing comma.
+fn element_bounds(input: &[u8]) -> Option<Vec<(usize, usize)>> {
+    let open = first_byte(input);
+    if input.get(open) != Some(&b'[') {
+        return None;
+    }
+    let mut bounds = Vec::new();
+    let mut start = open + 1;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut close = None;
+    for (i, b) in input.iter().enumerate().skip(open + 1) {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth > 0 => depth -= 1,
+            b',' | b']' if depth == 0 => {
+                let element = start + first_byte(&input[start..i]);
+                if element == i {
+                    // An empty element, fine only for an empty array
+                    if *b == b',' || !bounds.is_empty() {
+                        return None;
+                    }
+                } else {
+                    bounds.push((element, i));
+                }
+                if *b == b']' {
+                    close = Some(i);
+                    break;
+                }
+                start = i + 1;
+            }
+            b'}' => return None,
+            _ => {}
+        }
+    }
+    let close = close?;
+    if first_byte(&input[close + 1..]) != input.len() - close - 1 {
+        return None;
+    }
+    Some(bounds)
+}
+
+// Parses every line of NDJSON concurrently, giving the same results as
+// `NdjsonReader`
+pub fn parse_ndjson(input: &str) -> Vec<Result<JsonVal, NdjsonError>> {
+    parse_ndjson_with_options(input, Options::default())
+}
+
+// Like `NdjsonReader::with_options`, each line being parsed with `options`.
+// There is no maximum line length, as the whole input is already in memory.
+pub fn parse_ndjson_with_options(
+    input: &str,
+    options: Options,
+) -> Vec<Result<JsonVal, NdjsonError>> {
+    let lines: Vec<_> = input
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| (i + 1, trim_line(line)))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    lines
+        .into_par_iter()
+        .map(|(line, text)| {
+            parse_value(Tokenizer::from_str(text).with_options(options)).map_err(|err| {
+                NdjsonError {
+                    kind: NdjsonErrorKind::Parsing(err),
+                    line,
+                }
+            })
+        })
+        .collect()
+}
@@ -1,19 +1,14 @@
+pub use crate::number::Number;
+use crate::number::{is_valid_number, parse_f64, parse_number, NonFinite, NonFiniteError};
 use crate::tokenizer::*;
+use core::cmp::Ordering;
 use core::fmt::Display;
+use core::hash::{Hash, Hasher};
 use indexmap::IndexMap;
 
 pub type MapType<K, V> = IndexMap<K, V>;
 
-#[derive(Debug, PartialEq)]
-pub enum Number {
-    UnsignedInt(u64),
-    SignedInt(i64),
-    Float(f64),
-}
-
-impl Eq for Number {}
-
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum JsonVal {
     Null,
     Number(Number),
@@ -23,7 +18,71 @@ pub enum JsonVal {
     Object(MapType<String, JsonVal>),
 }
 
+// Values of different types are ordered as listed above. Objects are compared
+// without taking the order of their keys into account, same as `PartialEq`.
+impl PartialOrd for JsonVal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsonVal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use JsonVal::*;
+        match (self, other) {
+            (Null, Null) => Ordering::Equal,
+            (Number(a), Number(b)) => a.cmp(b),
+            (String(a), String(b)) => a.cmp(b),
+            (Boolean(a), Boolean(b)) => a.cmp(b),
+            (Array(a), Array(b)) => a.cmp(b),
+            (Object(a), Object(b)) => {
+                let mut a: Vec<_> = a.iter().collect();
+                let mut b: Vec<_> = b.iter().collect();
+                a.sort_unstable();
+                b.sort_unstable();
+                a.cmp(&b)
+            }
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for JsonVal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            JsonVal::Null => {}
+            JsonVal::Number(n) => n.hash(state),
+            JsonVal::String(s) => s.hash(state),
+            JsonVal::Boolean(b) => b.hash(state),
+            JsonVal::Array(arr) => arr.hash(state),
+            JsonVal::Object(obj) => {
+                // Every entry is hashed on its own and then combined with an
+                // operation that doesn't depend on the order
+                let entries = obj.iter().fold(0u64, |acc, entry| {
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    entry.hash(&mut hasher);
+                    acc.wrapping_add(hasher.finish())
+                });
+                obj.len().hash(state);
+                entries.hash(state);
+            }
+        }
+    }
+}
+
 impl JsonVal {
+    fn rank(&self) -> u8 {
+        match self {
+            JsonVal::Null => 0,
+            JsonVal::Number(_) => 1,
+            JsonVal::String(_) => 2,
+            JsonVal::Boolean(_) => 3,
+            JsonVal::Array(_) => 4,
+            JsonVal::Object(_) => 5,
+        }
+    }
+
     fn print_indent(&self, f: &mut std::fmt::Formatter<'_>, depth: u8) -> std::fmt::Result {
         for _ in 0..depth {
             write!(f, "    ")?;
@@ -35,11 +94,14 @@ impl JsonVal {
             JsonVal::Array(arr) => {
                 write!(f, "[")?;
                 if !arr.is_empty() {
-                    write!(f, "\n")?;
-                    for val in arr {
+                    writeln!(f)?;
+                    for (i, val) in arr.iter().enumerate() {
                         self.print_indent(f, depth + 1)?;
                         val.fmt_impl(f, depth + 1)?;
-                        write!(f, ",\n")?;
+                        if i != arr.len() - 1 {
+                            write!(f, ",")?;
+                        }
+                        writeln!(f)?;
                     }
                     self.print_indent(f, depth)?;
                 }
@@ -48,7 +110,7 @@ impl JsonVal {
             JsonVal::Object(obj) => {
                 write!(f, "{{")?;
                 if !obj.is_empty() {
-                    write!(f, "\n")?;
+                    writeln!(f)?;
                     for (i, (ident, val)) in obj.iter().enumerate() {
                         self.print_indent(f, depth + 1)?;
                         write!(f, "\"{}\": ", ident)?;
@@ -56,7 +118,7 @@ impl JsonVal {
                         if i != obj.len() - 1 {
                             write!(f, ",")?;
                         }
-                        write!(f, "\n")?;
+                        writeln!(f)?;
                     }
                 }
                 self.print_indent(f, depth)?;
@@ -71,43 +133,109 @@ impl JsonVal {
             JsonVal::Null => {
                 write!(f, "null")?;
             }
-            JsonVal::Number(num) => match num {
-                Number::Float(n) => {
-                    write!(f, "{}", n)?;
-                }
-                Number::UnsignedInt(n) => {
-                    write!(f, "{}", n)?;
-                }
-                Number::SignedInt(n) => {
-                    write!(f, "{}", n)?;
-                }
-            },
+            JsonVal::Number(num) => {
+                write!(f, "{}", num)?;
+            }
         }
         Ok(())
     }
 }
 
+impl JsonVal {
+    // `Display` always writes non-finite floats as `null`, this allows
+    // rejecting them instead
+    pub fn to_string_with(&self, non_finite: NonFinite) -> Result<String, NonFiniteError> {
+        match self.find_non_finite() {
+            Some(value) if non_finite == NonFinite::Error => Err(NonFiniteError { value }),
+            _ => Ok(self.to_string()),
+        }
+    }
+
+    // The first `NaN` or infinite float in the value
+    fn find_non_finite(&self) -> Option<f64> {
+        match self {
+            JsonVal::Number(Number::Float(n)) if !n.is_finite() => Some(*n),
+            JsonVal::Array(arr) => arr.iter().find_map(JsonVal::find_non_finite),
+            JsonVal::Object(obj) => obj.values().find_map(JsonVal::find_non_finite),
+            _ => None,
+        }
+    }
+}
+
 impl Display for JsonVal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.fmt_impl(f, 0)
     }
 }
 
+impl JsonVal {
+    // Same as `Display`, but without any whitespace
+    pub fn to_compact_string(&self) -> String {
+        let mut s = String::new();
+        // Writing to a `String` can't fail
+        let _ = self.fmt_compact(&mut s);
+        s
+    }
+
+    fn fmt_compact(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        match self {
+            JsonVal::Array(arr) => {
+                write!(f, "[")?;
+                for (i, val) in arr.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    val.fmt_compact(f)?;
+                }
+                write!(f, "]")
+            }
+            JsonVal::Object(obj) => {
+                write!(f, "{{")?;
+                for (i, (ident, val)) in obj.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "\"{}\":", ident)?;
+                    val.fmt_compact(f)?;
+                }
+                write!(f, "}}")
+            }
+            val => write!(f, "{}", val),
+        }
+    }
+}
+
 fn parse_object(
     tokenizer: &mut Tokenizer<impl Iterator<Item = char>>,
 ) -> Result<JsonVal, ParsingError> {
+    tokenizer.enter()?;
+    let limit = tokenizer.options().limits.max_members;
     let mut map = IndexMap::new();
+    // Duplicate keys count too, as they are parsed all the same
+    let mut len = 0;
+    // Members are separated by exactly one comma
+    let mut needs_comma = false;
     loop {
         let token = tokenizer.next_token()?;
         match token.kind {
             TokenKind::ClosedBracket => {
+                tokenizer.leave();
                 break Ok(JsonVal::Object(map));
             }
+            TokenKind::Ident(_) if needs_comma => {
+                break Err(ParsingError {
+                    kind: ParsingErrorKind::MissingEndingComma,
+                    loc: token.loc,
+                });
+            }
             TokenKind::Ident(_) => {
+                len += 1;
+                tokenizer.check_limit(len, limit, ParsingErrorKind::TooManyMembers, &token.loc)?;
                 let (ident, val) = parse_ident(token, tokenizer)?;
                 map.insert(ident, val);
+                needs_comma = true;
             }
-            TokenKind::Comma => {} // Ignore
+            TokenKind::Comma if needs_comma => needs_comma = false,
             _ => {
                 break Err(ParsingError {
                     kind: ParsingErrorKind::UnexpectedToken,
@@ -121,11 +249,27 @@ fn parse_object(
 fn parse_array(
     tokenizer: &mut Tokenizer<impl Iterator<Item = char>>,
 ) -> Result<JsonVal, ParsingError> {
+    tokenizer.enter()?;
+    let limit = tokenizer.options().limits.max_elements;
     let mut arr = Vec::new();
+    // Elements are separated by exactly one comma
+    let mut needs_comma = false;
     loop {
         let token = tokenizer.next_token()?;
+        if !matches!(token.kind, TokenKind::ClosedSqBracket | TokenKind::Comma) {
+            if needs_comma {
+                break Err(ParsingError {
+                    kind: ParsingErrorKind::MissingEndingComma,
+                    loc: token.loc,
+                });
+            }
+            let len = arr.len() + 1;
+            tokenizer.check_limit(len, limit, ParsingErrorKind::TooManyElements, &token.loc)?;
+            needs_comma = true;
+        }
         match token.kind {
             TokenKind::ClosedSqBracket => {
+                tokenizer.leave();
                 break Ok(JsonVal::Array(arr));
             }
 
@@ -141,7 +285,7 @@ fn parse_array(
                     loc: token.loc,
                 });
             }
-            TokenKind::Comma => {} // Ignore
+            TokenKind::Comma if needs_comma => needs_comma = false,
             _ => {
                 break Err(ParsingError {
                     kind: ParsingErrorKind::UnexpectedToken,
@@ -156,27 +300,43 @@ fn parse_string(str: String) -> Result<JsonVal, ParsingError> {
     Ok(JsonVal::String(str))
 }
 
-fn parse_val(
+pub(crate) fn parse_val(
     val: Token,
     tokenizer: &mut Tokenizer<impl Iterator<Item = char>>,
 ) -> Result<JsonVal, ParsingError> {
     match val.kind {
         TokenKind::Val(str) => {
+            tokenizer.add_node()?;
+            if tokenizer.options().json5 {
+                if let Some(num) = crate::json5::parse_number(&str) {
+                    return Ok(JsonVal::Number(num));
+                }
+            }
             let chars: Vec<_> = str.chars().collect();
 
-            if chars[0] == '"' && chars[chars.len() - 1] == '"' {
+            if let Some(inner) = str.strip_prefix('"').and_then(|str| str.strip_suffix('"')) {
                 // This is a string
-                parse_string(chars[1..chars.len() - 1].iter().collect())
-            } else if chars.iter().all(is_num_char) {
+                parse_string(inner.to_string())
+            } else if !chars.is_empty() && chars.iter().all(is_num_char) {
                 // This is a number
-                if chars
+                if tokenizer.options().arbitrary_precision {
+                    // Keep the text as is, only making sure it is a valid number
+                    let s: String = chars.iter().collect();
+                    if is_valid_number(&s) {
+                        Ok(JsonVal::Number(Number::Raw(s)))
+                    } else {
+                        Err(ParsingError {
+                            kind: ParsingErrorKind::InvalidToken,
+                            loc: val.loc,
+                        })
+                    }
+                } else if chars
                     .iter()
-                    .any(|c| c.to_ascii_lowercase() == 'e' || *c == '.')
+                    .any(|c| c.eq_ignore_ascii_case(&'e') || *c == '.')
                 {
                     // It is floating point
                     let s: String = chars.iter().collect();
-                    let num: Result<f64, _> = s.parse();
-                    if let Ok(num) = num {
+                    if let Some(num) = parse_f64(&s) {
                         Ok(JsonVal::Number(Number::Float(num)))
                     } else {
                         Err(ParsingError {
@@ -185,35 +345,22 @@ fn parse_val(
                         })
                     }
                 } else {
-                    // It is an int
+                    // It is an int, which can't have a `+` or leading zeros
                     let s: String = chars.iter().collect();
-                    if *chars.first().unwrap() == '-' {
-                        let num: Result<i64, _> = s.parse();
-                        if let Ok(num) = num {
-                            Ok(JsonVal::Number(Number::SignedInt(num)))
-                        } else {
-                            Err(ParsingError {
-                                kind: ParsingErrorKind::InvalidToken,
-                                loc: val.loc,
-                            })
-                        }
+                    if let Some(num) = parse_number(&s) {
+                        Ok(JsonVal::Number(num))
                     } else {
-                        let num: Result<u64, _> = s.parse();
-                        if let Ok(num) = num {
-                            Ok(JsonVal::Number(Number::UnsignedInt(num)))
-                        } else {
-                            Err(ParsingError {
-                                kind: ParsingErrorKind::InvalidToken,
-                                loc: val.loc,
-                            })
-                        }
+                        Err(ParsingError {
+                            kind: ParsingErrorKind::InvalidToken,
+                            loc: val.loc,
+                        })
                     }
                 }
-            } else if chars.len() == 4 && chars.iter().zip("true".chars()).all(|(&a, b)| a == b) {
+            } else if str == "true" {
                 Ok(JsonVal::Boolean(true))
-            } else if chars.len() == 5 && chars.iter().zip("false".chars()).all(|(&a, b)| a == b) {
+            } else if str == "false" {
                 Ok(JsonVal::Boolean(false))
-            } else if chars.iter().zip("null".chars()).all(|(&a, b)| a == b) {
+            } else if str == "null" {
                 Ok(JsonVal::Null)
             } else {
                 Err(ParsingError {
@@ -235,26 +382,31 @@ pub fn parse_ident(
     ident: Token,
     tokenizer: &mut Tokenizer<impl Iterator<Item = char>>,
 ) -> Result<(String, JsonVal), ParsingError> {
+    let TokenKind::Ident(ident) = ident.kind else {
+        return Err(ParsingError {
+            kind: ParsingErrorKind::UnexpectedToken,
+            loc: ident.loc,
+        });
+    };
     tokenizer.expect_token(TokenKind::Colon)?;
     let next_token = tokenizer.next_token()?;
-
-    if let TokenKind::Ident(ident) = ident.kind {
-        Ok((ident, parse_val(next_token, tokenizer)?))
-    } else {
-        unreachable!()
-    }
+    Ok((ident, parse_val(next_token, tokenizer)?))
 }
 
+// Accepts a value of any type at the top level, as RFC 8259 does, and fails if
+// anything other than whitespace follows it
 pub fn parse(
     mut tokenizer: Tokenizer<impl Iterator<Item = char>>,
 ) -> Result<JsonVal, ParsingError> {
     let token = tokenizer.next_token()?;
-    match token.kind {
-        TokenKind::OpenBracket => parse_object(&mut tokenizer),
-        TokenKind::OpenSqBracket => parse_array(&mut tokenizer),
-        _ => Err(ParsingError {
-            kind: ParsingErrorKind::InvalidStartingToken,
-            loc: token.loc,
-        }),
-    }
+    let val = parse_val(token, &mut tokenizer)?;
+    tokenizer.expect_token(TokenKind::End)?;
+    Ok(val)
+}
+
+// The same as `parse`, from when it only accepted arrays and objects
+pub fn parse_value(
+    tokenizer: Tokenizer<impl Iterator<Item = char>>,
+) -> Result<JsonVal, ParsingError> {
+    parse(tokenizer)
 }
@@ -0,0 +1,204 @@
+use crate::parser::{JsonVal, MapType};
+use crate::pointer::{parse_pointer, PointerError};
+use core::fmt::Display;
+
+// JSON Patch (RFC 6902)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    Add { path: String, value: JsonVal },
+    Remove { path: String },
+    Replace { path: String, value: JsonVal },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonVal },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch(pub Vec<PatchOp>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    // The patch document is not an array of valid operation objects
+    InvalidPatch,
+    Pointer(PointerError),
+    TestFailed,
+    // A value can't be moved into one of its children
+    MoveIntoChild,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatchError {
+    pub(crate) kind: PatchErrorKind,
+    // Index of the operation that failed
+    pub(crate) op: usize,
+}
+
+impl PatchError {
+    pub fn kind(&self) -> &PatchErrorKind {
+        &self.kind
+    }
+
+    pub fn op(&self) -> usize {
+        self.op
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            PatchErrorKind::Pointer(err) => write!(f, "operation {}: {}", self.op, err),
+            kind => write!(f, "operation {}: {:?}", self.op, kind),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+type Result<T> = std::result::Result<T, PatchError>;
+
+impl Patch {
+    pub fn from_json(patch: &JsonVal) -> Result<Patch> {
+        let JsonVal::Array(ops) = patch else {
+            return Err(PatchError {
+                kind: PatchErrorKind::InvalidPatch,
+                op: 0,
+            });
+        };
+        ops.iter()
+            .enumerate()
+            .map(|(i, op)| parse_op(op).map_err(|kind| PatchError { kind, op: i }))
+            .collect::<Result<_>>()
+            .map(Patch)
+    }
+
+    pub fn to_json(&self) -> JsonVal {
+        let string = |s: &str| JsonVal::String(s.to_string());
+        JsonVal::Array(
+            self.0
+                .iter()
+                .map(|op| {
+                    let mut map = MapType::new();
+                    let (name, path) = match op {
+                        PatchOp::Add { path, .. } => ("add", path),
+                        PatchOp::Remove { path } => ("remove", path),
+                        PatchOp::Replace { path, .. } => ("replace", path),
+                        PatchOp::Move { path, .. } => ("move", path),
+                        PatchOp::Copy { path, .. } => ("copy", path),
+                        PatchOp::Test { path, .. } => ("test", path),
+                    };
+                    map.insert("op".to_string(), string(name));
+                    map.insert("path".to_string(), string(path));
+                    match op {
+                        PatchOp::Add { value, .. }
+                        | PatchOp::Replace { value, .. }
+                        | PatchOp::Test { value, .. } => {
+                            map.insert("value".to_string(), value.clone());
+                        }
+                        PatchOp::Move { from, .. } | PatchOp::Copy { from, .. } => {
+                            map.insert("from".to_string(), string(from));
+                        }
+                        PatchOp::Remove { .. } => {}
+                    }
+                    JsonVal::Object(map)
+                })
+                .collect(),
+        )
+    }
+
+    // Applies every operation in order. If any of them fails `doc` is left
+    // untouched.
+    pub fn apply(&self, doc: &mut JsonVal) -> Result<()> {
+        let mut patched = doc.clone();
+        for (i, op) in self.0.iter().enumerate() {
+            apply_op(&mut patched, op).map_err(|kind| PatchError { kind, op: i })?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+}
+
+impl JsonVal {
+    pub fn apply_patch(&mut self, patch: &JsonVal) -> Result<()> {
+        Patch::from_json(patch)?.apply(self)
+    }
+}
+
+fn parse_op(op: &JsonVal) -> std::result::Result<PatchOp, PatchErrorKind> {
+    let JsonVal::Object(op) = op else {
+        return Err(PatchErrorKind::InvalidPatch);
+    };
+    let pointer = |member: &str| match op.get(member) {
+        Some(JsonVal::String(s)) => {
+            parse_pointer(s).map_err(PatchErrorKind::Pointer)?;
+            Ok(s.clone())
+        }
+        _ => Err(PatchErrorKind::InvalidPatch),
+    };
+    let value = || op.get("value").cloned().ok_or(PatchErrorKind::InvalidPatch);
+
+    let path = pointer("path")?;
+    match op.get("op") {
+        Some(JsonVal::String(name)) => match name.as_str() {
+            "add" => Ok(PatchOp::Add {
+                path,
+                value: value()?,
+            }),
+            "remove" => Ok(PatchOp::Remove { path }),
+            "replace" => Ok(PatchOp::Replace {
+                path,
+                value: value()?,
+            }),
+            "move" => Ok(PatchOp::Move {
+                from: pointer("from")?,
+                path,
+            }),
+            "copy" => Ok(PatchOp::Copy {
+                from: pointer("from")?,
+                path,
+            }),
+            "test" => Ok(PatchOp::Test {
+                path,
+                value: value()?,
+            }),
+            _ => Err(PatchErrorKind::InvalidPatch),
+        },
+        _ => Err(PatchErrorKind::InvalidPatch),
+    }
+}
+
+fn apply_op(doc: &mut JsonVal, op: &PatchOp) -> std::result::Result<(), PatchErrorKind> {
+    use PatchErrorKind::Pointer;
+    match op {
+        PatchOp::Add { path, value } => {
+            doc.pointer_insert(path, value.clone()).map_err(Pointer)?;
+        }
+        PatchOp::Remove { path } => {
+            doc.pointer_remove(path).map_err(Pointer)?;
+        }
+        PatchOp::Replace { path, value } => {
+            *doc.pointer_mut(path).map_err(Pointer)? = value.clone();
+        }
+        PatchOp::Move { from, path } => {
+            // `from` has to exist even when the value stays where it is
+            doc.pointer(from).map_err(Pointer)?;
+            if from != path {
+                if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                    return Err(PatchErrorKind::MoveIntoChild);
+                }
+                let value = doc.pointer_remove(from).map_err(Pointer)?;
+                doc.pointer_insert(path, value).map_err(Pointer)?;
+            }
+        }
+        PatchOp::Copy { from, path } => {
+            let value = doc.pointer(from).map_err(Pointer)?.clone();
+            doc.pointer_insert(path, value).map_err(Pointer)?;
+        }
+        PatchOp::Test { path, value } => {
+            if doc.pointer(path).map_err(Pointer)? != value {
+                return Err(PatchErrorKind::TestFailed);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::parser::JsonVal;
+use core::fmt::Display;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PointerErrorKind {
+    // The pointer is not empty and doesn't start with `/`
+    MissingLeadingSlash,
+    // A `~` not followed by `0` or `1`
+    InvalidEscape,
+    // Tried to go into a value that is not an array or an object
+    NotAContainer,
+    KeyNotFound,
+    // Not `-` or a number without leading zeros
+    InvalidIndex,
+    IndexOutOfBounds,
+    // The root can be replaced, but not removed
+    RemoveRoot,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PointerError {
+    pub(crate) kind: PointerErrorKind,
+    // Index of the reference token that failed, 0 being the first one
+    pub(crate) segment: usize,
+    pub(crate) token: String,
+}
+
+impl PointerError {
+    pub fn kind(&self) -> PointerErrorKind {
+        self.kind
+    }
+
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl Display for PointerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} at segment {} ({:?})",
+            self.kind, self.segment, self.token
+        )
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+type Result<T> = std::result::Result<T, PointerError>;
+
+fn error(kind: PointerErrorKind, segment: usize, token: &str) -> PointerError {
+    PointerError {
+        kind,
+        segment,
+        token: token.to_string(),
+    }
+}
+
+// Splits a pointer into its unescaped reference tokens
+pub(crate) fn parse_pointer(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(pointer) = pointer.strip_prefix('/') else {
+        return Err(error(PointerErrorKind::MissingLeadingSlash, 0, pointer));
+    };
+    pointer
+        .split('/')
+        .enumerate()
+        .map(|(segment, token)| {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c == '~' {
+                    match chars.next() {
+                        Some('0') => unescaped.push('~'),
+                        Some('1') => unescaped.push('/'),
+                        _ => return Err(error(PointerErrorKind::InvalidEscape, segment, token)),
+                    }
+                } else {
+                    unescaped.push(c);
+                }
+            }
+            Ok(unescaped)
+        })
+        .collect()
+}
+
+// Escapes a key so it can be used as a reference token
+pub(crate) fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn parse_index(token: &str, len: usize, segment: usize) -> Result<usize> {
+    let valid = token == "0" || (!token.starts_with('0') && !token.is_empty());
+    match token.parse::<usize>() {
+        Ok(i) if valid && token.bytes().all(|b| b.is_ascii_digit()) => {
+            if i < len {
+                Ok(i)
+            } else {
+                Err(error(PointerErrorKind::IndexOutOfBounds, segment, token))
+            }
+        }
+        _ => Err(error(PointerErrorKind::InvalidIndex, segment, token)),
+    }
+}
+
+fn child<'a>(val: &'a JsonVal, token: &str, segment: usize) -> Result<&'a JsonVal> {
+    match val {
+        JsonVal::Object(obj) => obj
+            .get(token)
+            .ok_or_else(|| error(PointerErrorKind::KeyNotFound, segment, token)),
+        JsonVal::Array(arr) => Ok(&arr[parse_index(token, arr.len(), segment)?]),
+        _ => Err(error(PointerErrorKind::NotAContainer, segment, token)),
+    }
+}
+
+fn child_mut<'a>(val: &'a mut JsonVal, token: &str, segment: usize) -> Result<&'a mut JsonVal> {
+    match val {
+        JsonVal::Object(obj) => obj
+            .get_mut(token)
+            .ok_or_else(|| error(PointerErrorKind::KeyNotFound, segment, token)),
+        JsonVal::Array(arr) => {
+            let i = parse_index(token, arr.len(), segment)?;
+            Ok(&mut arr[i])
+        }
+        _ => Err(error(PointerErrorKind::NotAContainer, segment, token)),
+    }
+}
+
+impl JsonVal {
+    // Looks up a value by its JSON Pointer (RFC 6901), like `/widget/window/title`
+    pub fn pointer(&self, pointer: &str) -> Result<&JsonVal> {
+        parse_pointer(pointer)?
+            .iter()
+            .enumerate()
+            .try_fold(self, |val, (segment, token)| child(val, token, segment))
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonVal> {
+        parse_pointer(pointer)?
+            .iter()
+            .enumerate()
+            .try_fold(self, |val, (segment, token)| child_mut(val, token, segment))
+    }
+
+    // Adds `val` at `pointer`, with the semantics of the JSON Patch `add`
+    // operation: the parent must exist, object members are replaced and array
+    // elements are shifted to make room. `-` appends to an array. Returns the
+    // replaced value, if any.
+    pub fn pointer_insert(&mut self, pointer: &str, val: JsonVal) -> Result<Option<JsonVal>> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, val)));
+        };
+        let segment = parents.len();
+        let parent = parents
+            .iter()
+            .enumerate()
+            .try_fold(self, |val, (segment, token)| child_mut(val, token, segment))?;
+        match parent {
+            JsonVal::Object(obj) => Ok(obj.insert(last.clone(), val)),
+            JsonVal::Array(arr) => {
+                let i = if last == "-" {
+                    arr.len()
+                } else {
+                    parse_index(last, arr.len() + 1, segment)?
+                };
+                arr.insert(i, val);
+                Ok(None)
+            }
+            _ => Err(error(PointerErrorKind::NotAContainer, segment, last)),
+        }
+    }
+
+    // Removes the value at `pointer` and returns it. Object members keep their
+    // order.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JsonVal> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Err(error(PointerErrorKind::RemoveRoot, 0, ""));
+        };
+        let segment = parents.len();
+        let parent = parents
+            .iter()
+            .enumerate()
+            .try_fold(self, |val, (segment, token)| child_mut(val, token, segment))?;
+        match parent {
+            JsonVal::Object(obj) => obj
+                .shift_remove(last.as_str())
+                .ok_or_else(|| error(PointerErrorKind::KeyNotFound, segment, last)),
+            JsonVal::Array(arr) => {
+                let i = parse_index(last, arr.len(), segment)?;
+                Ok(arr.remove(i))
+            }
+            _ => Err(error(PointerErrorKind::NotAContainer, segment, last)),
+        }
+    }
+}
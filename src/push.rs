@@ -0,0 +1,500 @@
+use crate::number::parse_number;
+use crate::parser::{JsonVal, MapType};
+use crate::tokenizer::{Limits, Loc, ParsingError, ParsingErrorKind};
+
+// A push parser, for input that arrives in chunks. Chunks are given with
+// `feed` and can be cut anywhere, even in the middle of a token or a UTF-8
+// character. When a token isn't complete yet `Event::NeedMoreData` is returned,
+// and parsing resumes from there once more data is fed. The default `Limits`
+// apply unless `with_limits` is used, and strings and numbers are checked
+// against them before they are complete, so they are never buffered in full.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    // Key of the next value in an object, with its escapes
+    Key(String),
+    // Anything that isn't an object or an array
+    Value(JsonVal),
+    NeedMoreData,
+    // The value has ended, and so has the input
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // A value at the top level or after a colon
+    Value,
+    ArrayFirst,
+    // A value after a comma
+    ArrayNext,
+    ObjectFirst,
+    // A key after a comma
+    ObjectNext,
+    Colon,
+    // A comma or the end of the container
+    AfterValue,
+    Done,
+}
+
+enum Token<T> {
+    Complete(T, usize),
+    // Bytes that can be skipped when trying again
+    Incomplete(usize),
+}
+
+#[derive(Debug)]
+pub struct PushParser {
+    buf: Vec<u8>,
+    // Bytes of `buf` that were already parsed
+    pos: usize,
+    // Bytes of the current token that were already checked
+    scanned: usize,
+    finished: bool,
+    state: State,
+    // `{` or `[` for each open container
+    stack: Vec<u8>,
+    // Elements or members of each open container, counting the current one
+    lens: Vec<usize>,
+    nodes: usize,
+    // Bytes parsed before `pos`, for `limits.max_input_size`
+    consumed: usize,
+    limits: Limits,
+    col: usize,
+    line: usize,
+    comma: Loc,
+    builder: Builder,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            scanned: 0,
+            finished: false,
+            state: State::Value,
+            stack: Vec::new(),
+            lens: Vec::new(),
+            nodes: 0,
+            consumed: 0,
+            limits: Limits::default(),
+            col: 0,
+            line: 1,
+            comma: Loc { col: 0, line: 1 },
+            builder: Builder::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // Moving the rest of a long token on every chunk would be quadratic
+        if self.pos >= self.buf.len() / 2 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    // Marks the end of the input, after which incomplete tokens are errors
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    // Location `offset` bytes after the parsed ones
+    fn loc(&self, offset: usize) -> Loc {
+        let mut loc = Loc {
+            col: self.col,
+            line: self.line,
+        };
+        for b in &self.buf[self.pos..self.pos + offset] {
+            advance_loc(&mut loc, *b);
+        }
+        loc.col += 1;
+        loc
+    }
+
+    fn error(&self, kind: ParsingErrorKind, offset: usize) -> ParsingError {
+        ParsingError {
+            kind,
+            loc: self.loc(offset),
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        let mut loc = Loc {
+            col: self.col,
+            line: self.line,
+        };
+        for b in &self.buf[self.pos..self.pos + len] {
+            advance_loc(&mut loc, *b);
+        }
+        self.col = loc.col;
+        self.line = loc.line;
+        self.pos += len;
+        self.consumed += len;
+        // Skipping nothing keeps the progress on the current token
+        if len > 0 {
+            self.scanned = 0;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let len = self.buf[self.pos..]
+            .iter()
+            .take_while(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+            .count();
+        self.consume(len);
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::AfterValue
+        };
+    }
+
+    // Ends a token that was cut off by the end of the available data
+    fn incomplete(
+        &mut self,
+        scanned: usize,
+        kind: ParsingErrorKind,
+    ) -> Result<Event, ParsingError> {
+        if self.finished {
+            Err(self.error(kind, 0))
+        } else {
+            self.scanned = scanned;
+            Ok(Event::NeedMoreData)
+        }
+    }
+
+    // Fails where the input goes over `limits.max_input_size`, as soon as
+    // that much was fed
+    fn check_input_size(&self) -> Result<(), ParsingError> {
+        match self.limits.max_input_size {
+            Some(max) if self.consumed + self.buf.len() - self.pos > max => {
+                Err(self.error(ParsingErrorKind::InputTooLarge, max - self.consumed))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Checks the limits at the start of a value, which is only counted once
+    // it is complete, as it may need more data first
+    fn check_val(&self) -> Result<(), ParsingError> {
+        use ParsingErrorKind::*;
+        if matches!(self.state, State::ArrayFirst | State::ArrayNext) {
+            self.check_len(false)?;
+        }
+        if self.limits.max_nodes.is_some_and(|max| self.nodes >= max) {
+            return Err(self.error(TooManyNodes, 0));
+        }
+        Ok(())
+    }
+
+    fn check_len(&self, object: bool) -> Result<(), ParsingError> {
+        use ParsingErrorKind::*;
+        let len = self.lens.last().copied().unwrap_or_default();
+        let (limit, kind) = if object {
+            (self.limits.max_members, TooManyMembers)
+        } else {
+            (self.limits.max_elements, TooManyElements)
+        };
+        match limit {
+            Some(max) if len > max => Err(self.error(kind, 0)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn next_event(&mut self) -> Result<Event, ParsingError> {
+        use ParsingErrorKind::*;
+        loop {
+            self.check_input_size()?;
+            self.skip_whitespace();
+            let Some(&b) = self.buf.get(self.pos) else {
+                return match (self.state, self.finished) {
+                    (State::Done, true) => Ok(Event::End),
+                    (_, false) => Ok(Event::NeedMoreData),
+                    (_, true) => Err(self.error(InvalidToken, 0)),
+                };
+            };
+            match self.state {
+                State::Done => return Err(self.error(UnexpectedToken, 0)),
+                State::ArrayFirst | State::ObjectFirst if b == b']' || b == b'}' => {
+                    let open = if b == b']' { b'[' } else { b'{' };
+                    if self.stack.last() != Some(&open) {
+                        return Err(self.error(UnexpectedToken, 0));
+                    }
+                    return Ok(self.close());
+                }
+                State::ArrayNext | State::ObjectNext if b == b']' || b == b'}' => {
+                    return Err(ParsingError {
+                        kind: InvalidTrailingComma,
+                        loc: self.comma.clone(),
+                    });
+                }
+                State::Value | State::ArrayFirst | State::ArrayNext => {
+                    self.check_val()?;
+                    return match b {
+                        b'{' | b'[' => {
+                            // Nothing recurses here, but dropping the value would
+                            if self
+                                .limits
+                                .max_depth
+                                .is_some_and(|max| self.stack.len() >= max)
+                            {
+                                return Err(self.error(DepthLimitExceeded, 0));
+                            }
+                            self.consume(1);
+                            self.stack.push(b);
+                            self.lens.push(1);
+                            self.nodes += 1;
+                            if b == b'{' {
+                                self.state = State::ObjectFirst;
+                                Ok(Event::StartObject)
+                            } else {
+                                self.state = State::ArrayFirst;
+                                Ok(Event::StartArray)
+                            }
+                        }
+                        b'"' => match self.lex_string()? {
+                            Token::Complete(s, len) => {
+                                self.consume(len);
+                                self.nodes += 1;
+                                self.after_value();
+                                Ok(Event::Value(JsonVal::String(s)))
+                            }
+                            Token::Incomplete(scanned) => {
+                                self.incomplete(scanned, UnterminatedString)
+                            }
+                        },
+                        _ => match self.lex_scalar()? {
+                            Token::Complete(val, len) => {
+                                self.consume(len);
+                                self.nodes += 1;
+                                self.after_value();
+                                Ok(Event::Value(val))
+                            }
+                            Token::Incomplete(scanned) => self.incomplete(scanned, InvalidToken),
+                        },
+                    };
+                }
+                State::ObjectFirst | State::ObjectNext => {
+                    if b != b'"' {
+                        return Err(self.error(UnexpectedToken, 0));
+                    }
+                    self.check_len(true)?;
+                    return match self.lex_string()? {
+                        Token::Complete(s, len) => {
+                            self.consume(len);
+                            self.state = State::Colon;
+                            Ok(Event::Key(s))
+                        }
+                        Token::Incomplete(scanned) => self.incomplete(scanned, UnterminatedString),
+                    };
+                }
+                State::Colon => {
+                    if b != b':' {
+                        return Err(self.error(UnexpectedToken, 0));
+                    }
+                    self.consume(1);
+                    self.state = State::Value;
+                }
+                State::AfterValue => {
+                    let open = self.stack.last().copied();
+                    match b {
+                        b',' => {
+                            self.comma = self.loc(0);
+                            self.consume(1);
+                            if let Some(len) = self.lens.last_mut() {
+                                *len += 1;
+                            }
+                            self.state = if open == Some(b'{') {
+                                State::ObjectNext
+                            } else {
+                                State::ArrayNext
+                            };
+                        }
+                        b']' if open == Some(b'[') => return Ok(self.close()),
+                        b'}' if open == Some(b'{') => return Ok(self.close()),
+                        _ => return Err(self.error(MissingEndingComma, 0)),
+                    }
+                }
+            }
+        }
+    }
+
+    fn close(&mut self) -> Event {
+        self.consume(1);
+        let open = self.stack.pop();
+        self.lens.pop();
+        self.after_value();
+        if open == Some(b'{') {
+            Event::EndObject
+        } else {
+            Event::EndArray
+        }
+    }
+
+    // Reads the string starting at the current position, keeping its escapes
+    fn lex_string(&self) -> Result<Token<String>, ParsingError> {
+        use ParsingErrorKind::*;
+        let bytes = &self.buf[self.pos..];
+        let mut i = self.scanned.max(1);
+        loop {
+            // `i - 1` bytes of the contents were read so far
+            if self.limits.max_string_length.is_some_and(|max| i - 1 > max) {
+                return Err(self.error(StringTooLong, 0));
+            }
+            match bytes.get(i) {
+                Some(b'"') => {
+                    return match core::str::from_utf8(&bytes[1..i]) {
+                        Ok(s) => Ok(Token::Complete(s.to_string(), i + 1)),
+                        Err(err) => Err(self.error(UnsupportedToken, 1 + err.valid_up_to())),
+                    };
+                }
+                Some(b'\\') => {
+                    let len = match bytes.get(i + 1) {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => 2,
+                        Some(b'u') => 6,
+                        Some(_) => return Err(self.error(InvalidToken, i)),
+                        None => return Ok(Token::Incomplete(i)),
+                    };
+                    let Some(escape) = bytes.get(i..i + len) else {
+                        return Ok(Token::Incomplete(i));
+                    };
+                    if len == 6 && !escape[2..].iter().all(u8::is_ascii_hexdigit) {
+                        return Err(self.error(InvalidToken, i));
+                    }
+                    i += len;
+                }
+                Some(0x00..=0x1f) => return Err(self.error(InvalidToken, i)),
+                Some(_) => i += 1,
+                None => return Ok(Token::Incomplete(i)),
+            }
+        }
+    }
+
+    // Reads a number or a literal like `true`. They are only complete once
+    // something follows them, or the input is finished.
+    fn lex_scalar(&self) -> Result<Token<JsonVal>, ParsingError> {
+        use ParsingErrorKind::*;
+        let bytes = &self.buf[self.pos..];
+        let Some(&first) = bytes.first() else {
+            return Ok(Token::Incomplete(0));
+        };
+        // The bytes before `scanned` are already known to be part of it
+        let rest = bytes[self.scanned..].iter();
+        let len = self.scanned
+            + match first {
+                b'-' | b'0'..=b'9' => rest
+                    .take_while(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                    .count(),
+                b if b.is_ascii_alphabetic() => {
+                    rest.take_while(|b| b.is_ascii_alphanumeric()).count()
+                }
+                b if !b.is_ascii() => return Err(self.error(UnsupportedToken, 0)),
+                _ => return Err(self.error(InvalidToken, 0)),
+            };
+        let number = first == b'-' || first.is_ascii_digit();
+        if number && self.limits.max_number_length.is_some_and(|max| len > max) {
+            return Err(self.error(NumberTooLong, 0));
+        }
+        if len == bytes.len() && !self.finished {
+            return Ok(Token::Incomplete(len));
+        }
+        // Only ASCII was read
+        let text = core::str::from_utf8(&bytes[..len]).unwrap_or_default();
+        let val = match text {
+            "true" => JsonVal::Boolean(true),
+            "false" => JsonVal::Boolean(false),
+            "null" => JsonVal::Null,
+            _ => match parse_number(text) {
+                Some(num) => JsonVal::Number(num),
+                None => return Err(self.error(InvalidToken, 0)),
+            },
+        };
+        Ok(Token::Complete(val, len))
+    }
+
+    // Parses as much as possible of the data fed so far, returning the value
+    // once it is complete. Use either this or `next_event`, not both.
+    pub fn value(&mut self) -> Result<Option<JsonVal>, ParsingError> {
+        loop {
+            match self.next_event()? {
+                Event::NeedMoreData | Event::End => return Ok(None),
+                event => {
+                    if let Some(val) = self.builder.add(event) {
+                        return Ok(Some(val));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn advance_loc(loc: &mut Loc, b: u8) {
+    if b == b'\n' {
+        loc.col = 0;
+        loc.line += 1;
+    } else if b & 0xC0 != 0x80 {
+        // Continuation bytes are part of the previous character
+        loc.col += 1;
+    }
+}
+
+// Builds a `JsonVal` from events
+#[derive(Debug, Default)]
+struct Builder {
+    stack: Vec<JsonVal>,
+    keys: Vec<String>,
+}
+
+impl Builder {
+    // Returns the value once the top level one is complete
+    fn add(&mut self, event: Event) -> Option<JsonVal> {
+        let val = match event {
+            Event::StartObject => {
+                self.stack.push(JsonVal::Object(MapType::new()));
+                return None;
+            }
+            Event::StartArray => {
+                self.stack.push(JsonVal::Array(Vec::new()));
+                return None;
+            }
+            Event::Key(key) => {
+                self.keys.push(key);
+                return None;
+            }
+            Event::EndObject | Event::EndArray => self.stack.pop()?,
+            Event::Value(val) => val,
+            Event::NeedMoreData | Event::End => return None,
+        };
+        match self.stack.last_mut() {
+            Some(JsonVal::Object(map)) => {
+                let key = self.keys.pop().unwrap_or_default();
+                map.insert(key, val);
+                None
+            }
+            Some(JsonVal::Array(arr)) => {
+                arr.push(val);
+                None
+            }
+            _ => Some(val),
+        }
+    }
+}
@@ -0,0 +1,578 @@
+use crate::borrowed::unescape;
+use crate::number::Number;
+use crate::parser::{JsonVal, MapType};
+use crate::pointer::escape_token;
+use core::fmt::Display;
+use regex::Regex;
+use std::collections::HashMap;
+
+// JSON Schema (draft 2020-12) validation. Only a subset of the keywords is
+// supported, and `$ref` can only point inside the schema itself, like
+// `#/$defs/address`. Unknown keywords are ignored, as the spec says.
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SchemaErrorKind {
+    // A keyword has a value of the wrong type
+    InvalidKeyword,
+    InvalidPattern,
+    // The `$ref` doesn't point to anything in the schema
+    UnresolvedRef,
+    // Only references within the same document are supported
+    UnsupportedRef,
+    // A `$ref` leads back to the same schema without going into the instance,
+    // so validation would never end
+    RecursiveRef,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SchemaError {
+    pub(crate) kind: SchemaErrorKind,
+    // JSON Pointer to the keyword in the schema
+    pub(crate) location: String,
+}
+
+impl SchemaError {
+    pub fn kind(&self) -> SchemaErrorKind {
+        self.kind
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} at {:?}", self.kind, self.location)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ValidationErrorKind {
+    // The schema is `false`
+    FalseSchema,
+    Type,
+    Enum,
+    Const,
+    Minimum,
+    Maximum,
+    ExclusiveMinimum,
+    ExclusiveMaximum,
+    MinLength,
+    MaxLength,
+    MinItems,
+    MaxItems,
+    Pattern,
+    Required(String),
+    AdditionalProperty(String),
+    AnyOf,
+    // Contains how many of the subschemas matched
+    OneOf(usize),
+    Not,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationError {
+    pub(crate) kind: ValidationErrorKind,
+    // JSON Pointer to the value that failed
+    pub(crate) instance_path: String,
+    // JSON Pointer to the keyword in the schema that failed
+    pub(crate) schema_path: String,
+}
+
+impl ValidationError {
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+
+    pub fn instance_path(&self) -> &str {
+        &self.instance_path
+    }
+
+    pub fn schema_path(&self) -> &str {
+        &self.schema_path
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} at {:?} (schema {:?})",
+            self.kind, self.instance_path, self.schema_path
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    String,
+    Integer,
+}
+
+type NodeId = usize;
+
+#[derive(Debug)]
+enum Keyword {
+    Type(Vec<Type>),
+    Enum(Vec<JsonVal>),
+    Const(JsonVal),
+    Minimum(Number),
+    Maximum(Number),
+    ExclusiveMinimum(Number),
+    ExclusiveMaximum(Number),
+    MinLength(u64),
+    MaxLength(u64),
+    MinItems(u64),
+    MaxItems(u64),
+    Pattern(Regex),
+    Required(Vec<String>),
+    Properties(Vec<(String, NodeId)>),
+    // Also holds the names listed in `properties`, which it doesn't apply to
+    AdditionalProperties(NodeId, Vec<String>),
+    PrefixItems(Vec<NodeId>),
+    // Also holds the length of `prefixItems`, as those are skipped
+    Items(NodeId, usize),
+    AllOf(Vec<NodeId>),
+    AnyOf(Vec<NodeId>),
+    OneOf(Vec<NodeId>),
+    Not(NodeId),
+    Ref(NodeId),
+}
+
+#[derive(Debug)]
+enum Node {
+    Bool(bool, String),
+    // Each keyword is stored along with its location in the schema
+    Keywords(Vec<(Keyword, String)>),
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    nodes: Vec<Node>,
+}
+
+struct Compiler<'a> {
+    root: &'a JsonVal,
+    nodes: Vec<Option<Node>>,
+    // Nodes that were already compiled, by location, so references to them are
+    // only compiled once and recursive schemas terminate
+    compiled: HashMap<String, NodeId>,
+}
+
+type Result<T> = std::result::Result<T, SchemaError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+fn error<T>(kind: SchemaErrorKind, location: &str) -> Result<T> {
+    Err(SchemaError {
+        kind,
+        location: location.to_string(),
+    })
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self, schema: &'a JsonVal, location: &str) -> Result<NodeId> {
+        if let Some(&id) = self.compiled.get(location) {
+            return Ok(id);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(None);
+        self.compiled.insert(location.to_string(), id);
+
+        let node = match schema {
+            JsonVal::Boolean(b) => Node::Bool(*b, location.to_string()),
+            JsonVal::Object(obj) => {
+                let mut keywords = Vec::new();
+                for (name, val) in obj {
+                    let location = format!("{}/{}", location, escape_token(name));
+                    if let Some(keyword) = self.keyword(obj, name, val, &location)? {
+                        keywords.push((keyword, location));
+                    }
+                }
+                Node::Keywords(keywords)
+            }
+            _ => return error(SchemaErrorKind::InvalidKeyword, location),
+        };
+        self.nodes[id] = Some(node);
+        Ok(id)
+    }
+
+    fn subschemas(&mut self, val: &'a JsonVal, location: &str) -> Result<Vec<NodeId>> {
+        match val {
+            JsonVal::Array(arr) if !arr.is_empty() => arr
+                .iter()
+                .enumerate()
+                .map(|(i, schema)| self.compile(schema, &format!("{}/{}", location, i)))
+                .collect(),
+            _ => error(SchemaErrorKind::InvalidKeyword, location),
+        }
+    }
+
+    fn keyword(
+        &mut self,
+        obj: &'a MapType<String, JsonVal>,
+        name: &str,
+        val: &'a JsonVal,
+        location: &str,
+    ) -> Result<Option<Keyword>> {
+        let invalid = || SchemaError {
+            kind: SchemaErrorKind::InvalidKeyword,
+            location: location.to_string(),
+        };
+        let number = || match val {
+            JsonVal::Number(n) => Ok(n.clone()),
+            _ => Err(invalid()),
+        };
+        // Any non-negative integer, including ones written like `2.0`
+        let count = || match val {
+            JsonVal::Number(n) => match n.as_u128() {
+                Some(n) => u64::try_from(n).ok(),
+                None => {
+                    let f = n.as_f64();
+                    (f.fract() == 0.0 && (0.0..=u64::MAX as f64).contains(&f)).then_some(f as u64)
+                }
+            }
+            .ok_or_else(invalid),
+            _ => Err(invalid()),
+        };
+        let keyword = match name {
+            "type" => {
+                let names = match val {
+                    JsonVal::String(s) => vec![s],
+                    JsonVal::Array(arr) => arr
+                        .iter()
+                        .map(|val| match val {
+                            JsonVal::String(s) => Ok(s),
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<_>>()?,
+                    _ => return Err(invalid()),
+                };
+                let types = names
+                    .into_iter()
+                    .map(|name| match name.as_str() {
+                        "null" => Ok(Type::Null),
+                        "boolean" => Ok(Type::Boolean),
+                        "object" => Ok(Type::Object),
+                        "array" => Ok(Type::Array),
+                        "number" => Ok(Type::Number),
+                        "string" => Ok(Type::String),
+                        "integer" => Ok(Type::Integer),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<_>>()?;
+                Keyword::Type(types)
+            }
+            "enum" => match val {
+                JsonVal::Array(arr) => Keyword::Enum(arr.clone()),
+                _ => return Err(invalid()),
+            },
+            "const" => Keyword::Const(val.clone()),
+            "minimum" => Keyword::Minimum(number()?),
+            "maximum" => Keyword::Maximum(number()?),
+            "exclusiveMinimum" => Keyword::ExclusiveMinimum(number()?),
+            "exclusiveMaximum" => Keyword::ExclusiveMaximum(number()?),
+            "minLength" => Keyword::MinLength(count()?),
+            "maxLength" => Keyword::MaxLength(count()?),
+            "minItems" => Keyword::MinItems(count()?),
+            "maxItems" => Keyword::MaxItems(count()?),
+            "pattern" => match val {
+                JsonVal::String(s) => match Regex::new(&unescape(s)) {
+                    Ok(regex) => Keyword::Pattern(regex),
+                    Err(_) => return error(SchemaErrorKind::InvalidPattern, location),
+                },
+                _ => return Err(invalid()),
+            },
+            "required" => match val {
+                JsonVal::Array(arr) => Keyword::Required(
+                    arr.iter()
+                        .map(|val| match val {
+                            JsonVal::String(s) => Ok(s.clone()),
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid()),
+            },
+            "properties" => match val {
+                JsonVal::Object(props) => Keyword::Properties(
+                    props
+                        .iter()
+                        .map(|(key, schema)| {
+                            let location = format!("{}/{}", location, escape_token(key));
+                            Ok((key.clone(), self.compile(schema, &location)?))
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid()),
+            },
+            "additionalProperties" => {
+                let known = match obj.get("properties") {
+                    Some(JsonVal::Object(props)) => props.keys().cloned().collect(),
+                    _ => vec![],
+                };
+                Keyword::AdditionalProperties(self.compile(val, location)?, known)
+            }
+            "prefixItems" => Keyword::PrefixItems(self.subschemas(val, location)?),
+            "items" => {
+                let skip = match obj.get("prefixItems") {
+                    Some(JsonVal::Array(arr)) => arr.len(),
+                    _ => 0,
+                };
+                Keyword::Items(self.compile(val, location)?, skip)
+            }
+            "allOf" => Keyword::AllOf(self.subschemas(val, location)?),
+            "anyOf" => Keyword::AnyOf(self.subschemas(val, location)?),
+            "oneOf" => Keyword::OneOf(self.subschemas(val, location)?),
+            "not" => Keyword::Not(self.compile(val, location)?),
+            "$ref" => {
+                let JsonVal::String(reference) = val else {
+                    return Err(invalid());
+                };
+                let Some(pointer) = reference.strip_prefix('#') else {
+                    return error(SchemaErrorKind::UnsupportedRef, location);
+                };
+                match self.root.pointer(pointer) {
+                    Ok(target) => Keyword::Ref(self.compile(target, pointer)?),
+                    Err(_) => return error(SchemaErrorKind::UnresolvedRef, location),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(keyword))
+    }
+}
+
+impl Schema {
+    pub fn compile(schema: &JsonVal) -> Result<Schema> {
+        let mut compiler = Compiler {
+            root: schema,
+            nodes: Vec::new(),
+            compiled: HashMap::new(),
+        };
+        compiler.compile(schema, "")?;
+        let schema = Schema {
+            nodes: compiler.nodes.into_iter().flatten().collect(),
+        };
+        let mut state = vec![Visit::New; schema.nodes.len()];
+        for id in 0..schema.nodes.len() {
+            schema.check_cycles(id, &mut state)?;
+        }
+        Ok(schema)
+    }
+
+    // Subschemas that apply to the same instance as the node itself
+    fn same_instance(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &str)> {
+        let keywords = match &self.nodes[id] {
+            Node::Keywords(keywords) => &keywords[..],
+            Node::Bool(..) => &[],
+        };
+        keywords.iter().flat_map(|(keyword, location)| {
+            let ids = match keyword {
+                Keyword::Ref(id) | Keyword::Not(id) => core::slice::from_ref(id),
+                Keyword::AllOf(ids) | Keyword::AnyOf(ids) | Keyword::OneOf(ids) => &ids[..],
+                _ => &[],
+            };
+            ids.iter().map(move |id| (*id, location.as_str()))
+        })
+    }
+
+    fn check_cycles(&self, id: NodeId, state: &mut [Visit]) -> Result<()> {
+        if state[id] != Visit::New {
+            return Ok(());
+        }
+        state[id] = Visit::InProgress;
+        for (next, location) in self.same_instance(id) {
+            match state[next] {
+                Visit::InProgress => return error(SchemaErrorKind::RecursiveRef, location),
+                _ => self.check_cycles(next, state)?,
+            }
+        }
+        state[id] = Visit::Done;
+        Ok(())
+    }
+
+    // Returns every error found, not only the first one
+    pub fn validate(&self, instance: &JsonVal) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_node(0, instance, "", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonVal) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    fn validate_node(
+        &self,
+        id: NodeId,
+        instance: &JsonVal,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let keywords = match &self.nodes[id] {
+            Node::Bool(true, _) => return,
+            Node::Bool(false, location) => {
+                errors.push(ValidationError {
+                    kind: ValidationErrorKind::FalseSchema,
+                    instance_path: path.to_string(),
+                    schema_path: location.clone(),
+                });
+                return;
+            }
+            Node::Keywords(keywords) => keywords,
+        };
+        for (keyword, location) in keywords {
+            let mut fail = |kind| {
+                errors.push(ValidationError {
+                    kind,
+                    instance_path: path.to_string(),
+                    schema_path: location.clone(),
+                })
+            };
+            use ValidationErrorKind as Kind;
+            match (keyword, instance) {
+                (Keyword::Type(types), _) if !types.iter().any(|ty| has_type(instance, *ty)) => {
+                    fail(Kind::Type)
+                }
+                (Keyword::Enum(vals), _) if !vals.contains(instance) => fail(Kind::Enum),
+                (Keyword::Const(val), _) if val != instance => fail(Kind::Const),
+                (Keyword::Minimum(min), JsonVal::Number(n)) if n < min => fail(Kind::Minimum),
+                (Keyword::Maximum(max), JsonVal::Number(n)) if n > max => fail(Kind::Maximum),
+                (Keyword::ExclusiveMinimum(min), JsonVal::Number(n)) if n <= min => {
+                    fail(Kind::ExclusiveMinimum)
+                }
+                (Keyword::ExclusiveMaximum(max), JsonVal::Number(n)) if n >= max => {
+                    fail(Kind::ExclusiveMaximum)
+                }
+                // Strings are stored with their escapes, which count as one
+                // character each once decoded
+                (Keyword::MinLength(min), JsonVal::String(s))
+                    if (unescape(s).chars().count() as u64) < *min =>
+                {
+                    fail(Kind::MinLength)
+                }
+                (Keyword::MaxLength(max), JsonVal::String(s))
+                    if (unescape(s).chars().count() as u64) > *max =>
+                {
+                    fail(Kind::MaxLength)
+                }
+                (Keyword::MinItems(min), JsonVal::Array(arr)) if (arr.len() as u64) < *min => {
+                    fail(Kind::MinItems)
+                }
+                (Keyword::MaxItems(max), JsonVal::Array(arr)) if (arr.len() as u64) > *max => {
+                    fail(Kind::MaxItems)
+                }
+                (Keyword::Pattern(regex), JsonVal::String(s)) if !regex.is_match(&unescape(s)) => {
+                    fail(Kind::Pattern)
+                }
+                (Keyword::Required(keys), JsonVal::Object(obj)) => {
+                    for key in keys {
+                        if !obj.contains_key(key) {
+                            fail(Kind::Required(key.clone()));
+                        }
+                    }
+                }
+                (Keyword::Properties(props), JsonVal::Object(obj)) => {
+                    for (key, id) in props {
+                        if let Some(val) = obj.get(key) {
+                            let path = format!("{}/{}", path, escape_token(key));
+                            self.validate_node(*id, val, &path, errors);
+                        }
+                    }
+                }
+                (Keyword::AdditionalProperties(id, known), JsonVal::Object(obj)) => {
+                    for (key, val) in obj {
+                        if known.contains(key) {
+                            continue;
+                        }
+                        let path = format!("{}/{}", path, escape_token(key));
+                        if let Node::Bool(false, _) = self.nodes[*id] {
+                            errors.push(ValidationError {
+                                kind: Kind::AdditionalProperty(key.clone()),
+                                instance_path: path,
+                                schema_path: location.clone(),
+                            });
+                        } else {
+                            self.validate_node(*id, val, &path, errors);
+                        }
+                    }
+                }
+                (Keyword::PrefixItems(ids), JsonVal::Array(arr)) => {
+                    for (i, (id, val)) in ids.iter().zip(arr).enumerate() {
+                        self.validate_node(*id, val, &format!("{}/{}", path, i), errors);
+                    }
+                }
+                (Keyword::Items(id, skip), JsonVal::Array(arr)) => {
+                    for (i, val) in arr.iter().enumerate().skip(*skip) {
+                        self.validate_node(*id, val, &format!("{}/{}", path, i), errors);
+                    }
+                }
+                (Keyword::AllOf(ids), _) => {
+                    for id in ids {
+                        self.validate_node(*id, instance, path, errors);
+                    }
+                }
+                (Keyword::AnyOf(ids), _)
+                    if !ids.iter().any(|id| self.is_valid_node(*id, instance, path)) =>
+                {
+                    fail(Kind::AnyOf)
+                }
+                (Keyword::OneOf(ids), _) => {
+                    let matched = ids
+                        .iter()
+                        .filter(|id| self.is_valid_node(**id, instance, path))
+                        .count();
+                    if matched != 1 {
+                        fail(Kind::OneOf(matched));
+                    }
+                }
+                (Keyword::Not(id), _) if self.is_valid_node(*id, instance, path) => fail(Kind::Not),
+                (Keyword::Ref(id), _) => self.validate_node(*id, instance, path, errors),
+                // The keyword passed, or doesn't apply to this type of value
+                _ => {}
+            }
+        }
+    }
+
+    fn is_valid_node(&self, id: NodeId, instance: &JsonVal, path: &str) -> bool {
+        let mut errors = Vec::new();
+        self.validate_node(id, instance, path, &mut errors);
+        errors.is_empty()
+    }
+}
+
+fn has_type(val: &JsonVal, ty: Type) -> bool {
+    match (val, ty) {
+        (JsonVal::Null, Type::Null)
+        | (JsonVal::Boolean(_), Type::Boolean)
+        | (JsonVal::Object(_), Type::Object)
+        | (JsonVal::Array(_), Type::Array)
+        | (JsonVal::Number(_), Type::Number)
+        | (JsonVal::String(_), Type::String) => true,
+        (JsonVal::Number(n), Type::Integer) => match n {
+            Number::UnsignedInt(_) | Number::SignedInt(_) => true,
+            Number::Float(_) | Number::Raw(_) => n.as_f64().fract() == 0.0,
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,491 @@
+use crate::bytes::{check_input_size, loc_at, scan_number, to_str, Counts, Lexer};
+use crate::number::parse_number;
+use crate::parser::{self, JsonVal};
+use crate::tape::Document;
+use crate::tokenizer::{Limits, Options, ParsingError, ParsingErrorKind, Tokenizer};
+
+// A two-stage parser, in the style of simdjson. The first stage finds the
+// offset of every structural character, quote and start of a number or
+// literal, 64 bytes at a time and using SSE2 or AVX2 when available. It also
+// records where strings have escapes or control characters, so the second
+// stage only walks the strings that have them. The
+// second stage builds the value, or the tape of `tape::Document`, by jumping
+// between those offsets. It accepts the same input as `parser::parse`, giving
+// the same values and, for invalid input, the same errors.
+
+// Like `bytes::parse`, with the default `Limits`
+pub fn parse(input: &[u8]) -> Result<JsonVal, ParsingError> {
+    parse_with_limits(input, Limits::default())
+}
+
+// Like `bytes::parse_with_limits`
+pub fn parse_with_limits(input: &[u8], limits: Limits) -> Result<JsonVal, ParsingError> {
+    check_input_size(input, limits)?;
+    let input = to_str(input)?;
+    let index = structural_index(input.as_bytes(), Backend::detect());
+    let mut parser = Parser::new(input, &index, limits);
+    let res = parser
+        .parse_val()
+        .and_then(|val| parser.finish().map(|()| val));
+    res.map_err(|err| reference_error(input, limits, err))
+}
+
+// Like `tape::Document::parse`, building the tape from the index
+pub fn parse_tape(input: &[u8]) -> Result<Document, ParsingError> {
+    parse_tape_with_limits(input, Limits::default())
+}
+
+pub fn parse_tape_with_limits(input: &[u8], limits: Limits) -> Result<Document, ParsingError> {
+    check_input_size(input, limits)?;
+    let input = to_str(input)?;
+    let index = structural_index(input.as_bytes(), Backend::detect());
+    Document::build(Parser::new(input, &index, limits))
+        .map_err(|err| reference_error(input, limits, err))
+}
+
+// The second stage reports errors like `bytes`, with other kinds and locations
+// than `parser::parse`. So on any error the input is parsed again with
+// `parser::parse`, for its error, which costs nothing on valid input.
+fn reference_error(input: &str, limits: Limits, err: ParsingError) -> ParsingError {
+    let options = Options {
+        limits,
+        ..Default::default()
+    };
+    parser::parse(Tokenizer::from_str(input).with_options(options))
+        .err()
+        .unwrap_or(err)
+}
+
+// Bitmasks of a block of 64 bytes, where bit `i` is for byte `i`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Masks {
+    quote: u64,
+    backslash: u64,
+    // `{`, `}`, `[`, `]`, `:` and `,`
+    op: u64,
+    whitespace: u64,
+    // Bytes below 0x20, which can't be in strings unescaped
+    control: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    // Only used by tests on x86_64
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    // Only created after checking that the CPU supports it
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Backend {
+    pub(crate) fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Backend::Avx2;
+            }
+            // Always there on x86_64
+            Backend::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::Scalar
+    }
+
+    pub(crate) fn masks(self, block: &[u8; 64]) -> Masks {
+        match self {
+            Backend::Scalar => scalar_masks(block),
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: SSE2 is always available on x86_64, and `Avx2` is only
+            // used when AVX2 is
+            Backend::Sse2 => unsafe { sse2_masks(block) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2_masks(block) },
+        }
+    }
+}
+
+fn scalar_masks(block: &[u8; 64]) -> Masks {
+    let mut masks = Masks::default();
+    for (i, b) in block.iter().enumerate() {
+        let bit = 1 << i;
+        match b {
+            b'"' => masks.quote |= bit,
+            b'\\' => masks.backslash |= bit,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => masks.op |= bit,
+            b' ' | b'\t' | b'\n' | b'\r' => masks.whitespace |= bit,
+            _ => {}
+        }
+        if *b < 0x20 {
+            masks.control |= bit;
+        }
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+fn sse2_masks(block: &[u8; 64]) -> Masks {
+    use core::arch::x86_64::*;
+    let mut masks = Masks::default();
+    for i in 0..4 {
+        // SAFETY: the 16 bytes read are inside `block`
+        let chunk = unsafe { _mm_loadu_si128(block.as_ptr().add(i * 16) as *const __m128i) };
+        let eq = |c: u8| {
+            let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(chunk, _mm_set1_epi8(c as i8)));
+            (mask as u16 as u64) << (i * 16)
+        };
+        masks.quote |= eq(b'"');
+        masks.backslash |= eq(b'\\');
+        masks.op |= eq(b'{') | eq(b'}') | eq(b'[') | eq(b']') | eq(b':') | eq(b',');
+        masks.whitespace |= eq(b' ') | eq(b'\t') | eq(b'\n') | eq(b'\r');
+        // Saturating subtraction only leaves zero for bytes up to 0x1f
+        let low = _mm_subs_epu8(chunk, _mm_set1_epi8(0x1f));
+        let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(low, _mm_setzero_si128()));
+        masks.control |= (mask as u16 as u64) << (i * 16);
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn avx2_masks(block: &[u8; 64]) -> Masks {
+    use core::arch::x86_64::*;
+    let mut masks = Masks::default();
+    for i in 0..2 {
+        // SAFETY: the 32 bytes read are inside `block`
+        let chunk = unsafe { _mm256_loadu_si256(block.as_ptr().add(i * 32) as *const __m256i) };
+        let eq = |c: u8| {
+            let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(c as i8)));
+            (mask as u32 as u64) << (i * 32)
+        };
+        masks.quote |= eq(b'"');
+        masks.backslash |= eq(b'\\');
+        masks.op |= eq(b'{') | eq(b'}') | eq(b'[') | eq(b']') | eq(b':') | eq(b',');
+        masks.whitespace |= eq(b' ') | eq(b'\t') | eq(b'\n') | eq(b'\r');
+        let low = _mm256_subs_epu8(chunk, _mm256_set1_epi8(0x1f));
+        let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(low, _mm256_setzero_si256()));
+        masks.control |= (mask as u32 as u64) << (i * 32);
+    }
+    masks
+}
+
+// Sets every bit from an opening quote up to, but not including, its closing
+// quote
+fn prefix_xor(mut x: u64) -> u64 {
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
+}
+
+// What the first stage finds, all as offsets in increasing order
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Index {
+    // Structural characters, quotes and starts of numbers and literals, so
+    // every opening quote is followed by its closing one
+    offsets: Vec<usize>,
+    // Backslashes and control characters inside strings
+    string_escapes: Vec<usize>,
+}
+
+// Pushes the offset of every set bit of `bits`
+fn push_bits(offsets: &mut Vec<usize>, base: usize, mut bits: u64) {
+    while bits != 0 {
+        offsets.push(base + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+    }
+}
+
+// The first stage, finding the offsets the second stage jumps between
+pub(crate) fn structural_index(input: &[u8], backend: Backend) -> Index {
+    let mut index = Index {
+        offsets: Vec::with_capacity(input.len() / 4),
+        ..Default::default()
+    };
+    // State carried over from the previous block
+    let mut in_string = 0u64;
+    let mut escape_next = false;
+    let mut in_scalar = false;
+
+    for (block_num, chunk) in input.chunks(64).enumerate() {
+        // The last block is padded with whitespace
+        let masks = match chunk.try_into() {
+            Ok(block) => backend.masks(block),
+            Err(_) => {
+                let mut block = [b' '; 64];
+                block[..chunk.len()].copy_from_slice(chunk);
+                backend.masks(&block)
+            }
+        };
+
+        // Backslashes are rare, so escaped characters are found one by one
+        let mut escaped = escape_next as u64;
+        escape_next = false;
+        let mut backslashes = masks.backslash;
+        while backslashes != 0 {
+            let i = backslashes.trailing_zeros();
+            backslashes &= backslashes - 1;
+            if escaped & (1 << i) == 0 {
+                if i == 63 {
+                    escape_next = true;
+                } else {
+                    escaped |= 1 << (i + 1);
+                }
+            }
+        }
+
+        let quotes = masks.quote & !escaped;
+        let strings = prefix_xor(quotes) ^ in_string;
+        in_string = ((strings as i64) >> 63) as u64;
+        let outside = !(strings | quotes);
+        let scalars = outside & !masks.op & !masks.whitespace;
+        let scalar_starts = scalars & !((scalars << 1) | in_scalar as u64);
+        in_scalar = scalars >> 63 == 1;
+
+        let base = block_num * 64;
+        let structurals = (masks.op & outside) | quotes | scalar_starts;
+        push_bits(&mut index.offsets, base, structurals);
+        let escapes = (masks.backslash | masks.control) & strings & !quotes;
+        push_bits(&mut index.string_escapes, base, escapes);
+    }
+    index
+}
+
+// The second stage, giving `bytes::Lexer` the tokens at the offsets of the
+// index, without looking at the bytes in between
+struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    index: &'a Index,
+    // Positions in `index.offsets` and `index.string_escapes`
+    next: usize,
+    next_escape: usize,
+    // Where a number or literal was followed by bytes that aren't part of it,
+    // which are then the next token, like in `bytes::Parser`
+    rest: Option<usize>,
+    counts: Counts,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, index: &'a Index, limits: Limits) -> Self {
+        Self {
+            input,
+            bytes: input.as_bytes(),
+            index,
+            next: 0,
+            next_escape: 0,
+            rest: None,
+            counts: Counts::new(limits),
+        }
+    }
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(pos) = self.rest.take() {
+            return Some(pos);
+        }
+        let pos = self.index.offsets.get(self.next).copied();
+        self.next += 1;
+        pos
+    }
+
+    fn peek(&self) -> Option<usize> {
+        self.rest
+            .or_else(|| self.index.offsets.get(self.next).copied())
+    }
+
+    // Where the next token is, or the end of the input
+    fn peek_pos(&self) -> usize {
+        self.peek().unwrap_or(self.bytes.len())
+    }
+
+    fn error(&self, kind: ParsingErrorKind, pos: usize) -> ParsingError {
+        ParsingError {
+            kind,
+            loc: loc_at(self.bytes, pos),
+        }
+    }
+
+    fn parse_number(&mut self, pos: usize) -> Result<JsonVal, ParsingError> {
+        let (end, num) = scan_number(self.bytes, pos);
+        self.counts
+            .number(end - pos)
+            .map_err(|kind| self.error(kind, pos))?;
+        let num = num
+            .or_else(|| parse_number(&self.input[pos..end]))
+            .ok_or_else(|| self.error(ParsingErrorKind::InvalidToken, pos))?;
+        self.scalar_end(end);
+        Ok(JsonVal::Number(num))
+    }
+
+    fn parse_literal(&mut self, pos: usize) -> Result<JsonVal, ParsingError> {
+        let len = self.bytes[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric())
+            .count();
+        let val = match &self.bytes[pos..pos + len] {
+            b"true" => JsonVal::Boolean(true),
+            b"false" => JsonVal::Boolean(false),
+            b"null" => JsonVal::Null,
+            _ => return Err(self.error(ParsingErrorKind::InvalidToken, pos)),
+        };
+        self.scalar_end(pos + len);
+        Ok(val)
+    }
+
+    // Numbers and literals go on until the next offset, so anything else
+    // after them, up to it, is the next token
+    fn scalar_end(&mut self, end: usize) {
+        if !matches!(
+            self.bytes.get(end),
+            None | Some(
+                b' ' | b'\t' | b'\n' | b'\r' | b'"' | b'{' | b'}' | b'[' | b']' | b':' | b','
+            )
+        ) {
+            self.rest = Some(end);
+        }
+    }
+
+    // Parses the string starting at the quote at `quote`. The first stage
+    // already found where it ends, so only strings with escapes or control
+    // characters, and unterminated ones, are walked to check them.
+    fn parse_string(&mut self, quote: usize) -> Result<&'a str, ParsingError> {
+        let s = self.find_string(quote)?;
+        self.counts
+            .string(s.len())
+            .map_err(|kind| self.error(kind, quote))?;
+        Ok(s)
+    }
+
+    // The closing quote is the next offset, if the string is terminated
+    fn find_string(&mut self, quote: usize) -> Result<&'a str, ParsingError> {
+        let Some(&end) = self.index.offsets.get(self.next) else {
+            return self.walk_string(quote);
+        };
+        self.next += 1;
+        let escapes = &self.index.string_escapes;
+        while escapes
+            .get(self.next_escape)
+            .is_some_and(|&pos| pos < quote)
+        {
+            self.next_escape += 1;
+        }
+        if escapes.get(self.next_escape).is_some_and(|&pos| pos < end) {
+            return self.walk_string(quote);
+        }
+        Ok(&self.input[quote + 1..end])
+    }
+
+    fn walk_string(&self, quote: usize) -> Result<&'a str, ParsingError> {
+        use ParsingErrorKind::*;
+        let start = quote + 1;
+        let mut pos = start;
+        loop {
+            match self.bytes.get(pos) {
+                Some(b'"') => break Ok(&self.input[start..pos]),
+                Some(b'\\') => match self.bytes.get(pos + 1) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => pos += 2,
+                    Some(b'u')
+                        if self
+                            .bytes
+                            .get(pos + 2..pos + 6)
+                            .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) =>
+                    {
+                        pos += 6
+                    }
+                    _ => break Err(self.error(InvalidToken, pos)),
+                },
+                Some(0x00..=0x1f) => break Err(self.error(InvalidToken, pos)),
+                Some(_) => pos += 1,
+                None => break Err(self.error(InvalidToken, quote)),
+            }
+        }
+    }
+}
+
+impl<'a> Lexer<'a> for Parser<'a> {
+    fn peek_val(&mut self) -> Option<u8> {
+        self.peek().map(|pos| self.bytes[pos])
+    }
+
+    fn enter(&mut self) -> Result<(), ParsingError> {
+        let pos = self.peek_pos();
+        self.counts.enter().map_err(|kind| self.error(kind, pos))?;
+        self.next();
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.counts.leave();
+    }
+
+    fn is_empty(&mut self, end: u8) -> bool {
+        let empty = self.peek_val() == Some(end);
+        if empty {
+            self.next();
+        }
+        empty
+    }
+
+    fn check_len(&mut self, len: usize, close: u8) -> Result<(), ParsingError> {
+        self.counts
+            .len(len, close == b'}')
+            .map_err(|kind| self.error(kind, self.peek_pos()))
+    }
+
+    fn parse_ident(&mut self) -> Result<&'a str, ParsingError> {
+        use ParsingErrorKind::*;
+        let ident = match self.next() {
+            Some(pos) if self.bytes[pos] == b'"' => self.parse_string(pos)?,
+            pos => return Err(self.error(UnexpectedToken, pos.unwrap_or(self.bytes.len()))),
+        };
+        match self.next() {
+            Some(pos) if self.bytes[pos] == b':' => Ok(ident),
+            pos => Err(self.error(UnexpectedToken, pos.unwrap_or(self.bytes.len()))),
+        }
+    }
+
+    fn parse_string_val(&mut self) -> Result<&'a str, ParsingError> {
+        let pos = self.peek_pos();
+        self.counts.node().map_err(|kind| self.error(kind, pos))?;
+        self.next();
+        self.parse_string(pos)
+    }
+
+    fn parse_scalar(&mut self) -> Result<JsonVal, ParsingError> {
+        use ParsingErrorKind::*;
+        let pos = self.peek_pos();
+        self.counts.node().map_err(|kind| self.error(kind, pos))?;
+        match self.next().map(|pos| self.bytes[pos]) {
+            Some(b) if b == b'-' || b.is_ascii_digit() => self.parse_number(pos),
+            Some(b) if b.is_ascii_alphabetic() => self.parse_literal(pos),
+            Some(b) if !b.is_ascii() => Err(self.error(UnsupportedToken, pos)),
+            _ => Err(self.error(InvalidToken, pos)),
+        }
+    }
+
+    fn parse_separator(&mut self, end: u8) -> Result<bool, ParsingError> {
+        use ParsingErrorKind::*;
+        match self.next() {
+            Some(comma) if self.bytes[comma] == b',' => match self.peek() {
+                Some(pos) if self.bytes[pos] == end => Err(self.error(InvalidTrailingComma, comma)),
+                _ => Ok(false),
+            },
+            Some(pos) if self.bytes[pos] == end => Ok(true),
+            Some(pos) => Err(self.error(MissingEndingComma, pos)),
+            None => Err(self.error(MissingEndingComma, self.bytes.len())),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), ParsingError> {
+        match self.next() {
+            Some(pos) => Err(self.error(ParsingErrorKind::UnexpectedToken, pos)),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::parser::{parse_val, JsonVal};
+use crate::tokenizer::{ParsingError, TokenKind, Tokenizer};
+
+// Parses a stream of concatenated values, like `{"a":1}{"b":2}` or `1 2 3`.
+// Values can also be separated by the record separator of JSON text
+// sequences (RFC 7464), so `\x1e{"a":1}\n\x1e{"b":2}\n` works too.
+pub struct StreamParser<Iter: Iterator<Item = char>> {
+    tokenizer: Tokenizer<Iter>,
+    done: bool,
+}
+
+impl<Iter: Iterator<Item = char>> StreamParser<Iter> {
+    pub fn new(tokenizer: Tokenizer<Iter>) -> Self {
+        Self {
+            tokenizer,
+            done: false,
+        }
+    }
+}
+
+// Yields every value with the byte offset where it starts. Parsing stops after
+// the first error, as there is no way to know where the next value starts.
+impl<Iter: Iterator<Item = char>> Iterator for StreamParser<Iter> {
+    type Item = Result<(usize, JsonVal), ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let offset = self.tokenizer.skip_to_next_value();
+        // Only the first token of a value can be directly followed by the
+        // next one, values inside arrays and objects still need commas
+        self.tokenizer.top_level_stream = true;
+        let token = self.tokenizer.next_token();
+        self.tokenizer.top_level_stream = false;
+        let res = match token {
+            Ok(token) if token.kind == TokenKind::End => {
+                self.done = true;
+                return None;
+            }
+            Ok(token) => parse_val(token, &mut self.tokenizer),
+            Err(err) => Err(err),
+        };
+        if res.is_err() {
+            self.done = true;
+        }
+        Some(res.map(|val| (offset, val)))
+    }
+}
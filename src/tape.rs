@@ -0,0 +1,291 @@
+use crate::bytes::{check_input_size, to_str, Lexer, Parser};
+use crate::number::Number;
+use crate::parser::{JsonVal, MapType};
+use crate::tokenizer::{Limits, ParsingError};
+
+// A document stored as a flat list of entries, like simdjson's tape, instead
+// of a tree. Values come one after the other in the order they appear in the
+// input, each container followed by its elements and each key by its value.
+// All the strings share a single buffer.
+#[derive(Debug, Clone)]
+pub struct Document {
+    tape: Vec<Entry>,
+    strings: String,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    // Range in `Document::strings`, with the escapes left as they are
+    String { start: usize, end: usize },
+    // `end` is the index of the entry after the last element, so containers
+    // can be skipped in one step
+    Array { len: usize, end: usize },
+    Object { len: usize, end: usize },
+}
+
+impl Document {
+    // Accepts a value of any type at the top level, like `bytes::parse`, with
+    // the default `Limits`
+    pub fn parse(input: &[u8]) -> Result<Document, ParsingError> {
+        Self::parse_with_limits(input, Limits::default())
+    }
+
+    // Like `bytes::parse_with_limits`
+    pub fn parse_with_limits(input: &[u8], limits: Limits) -> Result<Document, ParsingError> {
+        check_input_size(input, limits)?;
+        Self::build(Parser::new(to_str(input)?).with_limits(limits))
+    }
+
+    // Also used by `simd`, building the tape from its index
+    pub(crate) fn build<'a>(lexer: impl Lexer<'a>) -> Result<Document, ParsingError> {
+        let mut builder = Builder {
+            lexer,
+            doc: Document {
+                tape: Vec::new(),
+                strings: String::new(),
+            },
+        };
+        builder.parse_val()?;
+        builder.lexer.finish()?;
+        Ok(builder.doc)
+    }
+
+    pub fn root(&self) -> Cursor<'_> {
+        Cursor {
+            doc: self,
+            index: 0,
+        }
+    }
+}
+
+impl From<&Document> for JsonVal {
+    fn from(doc: &Document) -> Self {
+        doc.root().into()
+    }
+}
+
+struct Builder<L> {
+    lexer: L,
+    doc: Document,
+}
+
+impl<'a, L: Lexer<'a>> Builder<L> {
+    fn push_string(&mut self, s: &str) {
+        let start = self.doc.strings.len();
+        self.doc.strings.push_str(s);
+        self.doc.tape.push(Entry::String {
+            start,
+            end: self.doc.strings.len(),
+        });
+    }
+
+    fn parse_val(&mut self) -> Result<(), ParsingError> {
+        match self.lexer.peek_val() {
+            Some(open @ (b'{' | b'[')) => {
+                self.lexer.enter()?;
+                self.parse_container(open)?;
+                self.lexer.leave();
+                Ok(())
+            }
+            Some(b'"') => {
+                let s = self.lexer.parse_string_val()?;
+                self.push_string(s);
+                Ok(())
+            }
+            _ => {
+                let entry = match self.lexer.parse_scalar()? {
+                    JsonVal::Boolean(b) => Entry::Boolean(b),
+                    JsonVal::Number(num) => Entry::Number(num),
+                    _ => Entry::Null,
+                };
+                self.doc.tape.push(entry);
+                Ok(())
+            }
+        }
+    }
+
+    fn parse_container(&mut self, open: u8) -> Result<(), ParsingError> {
+        let object = open == b'{';
+        let close = if object { b'}' } else { b']' };
+        let index = self.doc.tape.len();
+        // Filled in once the end is known
+        self.doc.tape.push(Entry::Null);
+        let mut len = 0;
+        if !self.lexer.is_empty(close) {
+            loop {
+                self.lexer.check_len(len + 1, close)?;
+                if object {
+                    let ident = self.lexer.parse_ident()?;
+                    self.push_string(ident);
+                }
+                self.parse_val()?;
+                len += 1;
+                if self.lexer.parse_separator(close)? {
+                    break;
+                }
+            }
+        }
+        let end = self.doc.tape.len();
+        self.doc.tape[index] = if object {
+            Entry::Object { len, end }
+        } else {
+            Entry::Array { len, end }
+        };
+        Ok(())
+    }
+}
+
+// A position in a `Document`, cheap to copy around
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor<'a> {
+    doc: &'a Document,
+    index: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn entry(&self) -> &'a Entry {
+        &self.doc.tape[self.index]
+    }
+
+    fn at_index(&self, index: usize) -> Cursor<'a> {
+        Cursor {
+            doc: self.doc,
+            index,
+        }
+    }
+
+    // Index of the entry after this value
+    fn next_index(&self) -> usize {
+        match self.entry() {
+            Entry::Array { end, .. } | Entry::Object { end, .. } => *end,
+            _ => self.index + 1,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.entry(), Entry::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.entry() {
+            Entry::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&'a Number> {
+        match self.entry() {
+            Entry::Number(num) => Some(num),
+            _ => None,
+        }
+    }
+
+    // Same as the text of a `JsonVal::String`, with its escapes
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.entry() {
+            Entry::String { start, end } => Some(&self.doc.strings[*start..*end]),
+            _ => None,
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.entry(), Entry::Array { .. })
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.entry(), Entry::Object { .. })
+    }
+
+    // Number of elements or members, counting duplicate keys
+    pub fn len(&self) -> Option<usize> {
+        match self.entry() {
+            Entry::Array { len, .. } | Entry::Object { len, .. } => Some(*len),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    // Element `i` of an array
+    pub fn at(&self, i: usize) -> Option<Cursor<'a>> {
+        if !self.is_array() {
+            return None;
+        }
+        self.elements().nth(i)
+    }
+
+    // Value of `key` in an object. With duplicate keys the last one is used,
+    // like when converting to `JsonVal`
+    pub fn get(&self, key: &str) -> Option<Cursor<'a>> {
+        if !self.is_object() {
+            return None;
+        }
+        self.members()
+            .filter(|(k, _)| *k == key)
+            .last()
+            .map(|(_, val)| val)
+    }
+
+    // The elements of an array, empty for anything else
+    pub fn elements(&self) -> impl Iterator<Item = Cursor<'a>> {
+        let (mut index, end) = match self.entry() {
+            Entry::Array { end, .. } => (self.index + 1, *end),
+            _ => (0, 0),
+        };
+        let cursor = *self;
+        core::iter::from_fn(move || {
+            (index < end).then(|| {
+                let element = cursor.at_index(index);
+                index = element.next_index();
+                element
+            })
+        })
+    }
+
+    // The keys and values of an object, empty for anything else
+    pub fn members(&self) -> impl Iterator<Item = (&'a str, Cursor<'a>)> {
+        let (mut index, end) = match self.entry() {
+            Entry::Object { end, .. } => (self.index + 1, *end),
+            _ => (0, 0),
+        };
+        let cursor = *self;
+        core::iter::from_fn(move || {
+            (index < end).then(|| {
+                let key = cursor.at_index(index).as_str().unwrap_or_default();
+                let val = cursor.at_index(index + 1);
+                index = val.next_index();
+                (key, val)
+            })
+        })
+    }
+}
+
+impl From<Cursor<'_>> for JsonVal {
+    fn from(cursor: Cursor<'_>) -> Self {
+        match cursor.entry() {
+            Entry::Null => JsonVal::Null,
+            Entry::Boolean(b) => JsonVal::Boolean(*b),
+            Entry::Number(num) => JsonVal::Number(num.clone()),
+            Entry::String { .. } => {
+                JsonVal::String(cursor.as_str().unwrap_or_default().to_string())
+            }
+            Entry::Array { len, .. } => {
+                let mut arr = Vec::with_capacity(*len);
+                arr.extend(cursor.elements().map(JsonVal::from));
+                JsonVal::Array(arr)
+            }
+            Entry::Object { .. } => {
+                let mut map = MapType::new();
+                for (key, val) in cursor.members() {
+                    map.insert(key.to_string(), val.into());
+                }
+                JsonVal::Object(map)
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::json5;
 use core::iter::Peekable;
 use core::str::Chars;
 
@@ -18,33 +19,110 @@ pub(crate) enum TokenKind {
     End,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub(crate) struct Loc {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loc {
     pub(crate) col: usize,
     pub(crate) line: usize,
 }
 
+impl Loc {
+    // Starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // Starting at 1
+    pub fn col(&self) -> usize {
+        self.col
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Token {
     pub(crate) kind: TokenKind,
     pub(crate) loc: Loc,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    // Parse every number as `Number::Raw`, keeping its original text
+    pub arbitrary_precision: bool,
+    // Skip `//` and `/* */` comments, like in JSONC
+    pub comments: bool,
+    // Allow a comma after the last element of an array or object
+    pub trailing_commas: bool,
+    // Parse JSON5, which also enables `comments` and `trailing_commas`
+    pub json5: bool,
+    pub limits: Limits,
+}
+
+// Limits for parsing untrusted input, where `None` means there is no limit.
+// By default only the nesting is limited, to `DEFAULT_MAX_DEPTH` levels, as
+// the parsers recurse into arrays and objects and would otherwise overflow the
+// stack. Every parser checks all of them, failing at the start of whatever
+// goes over, apart from the input size, which fails where the input does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    // Nesting of arrays and objects
+    pub max_depth: Option<usize>,
+    // In bytes
+    pub max_input_size: Option<usize>,
+    // In bytes, for both keys and values, before decoding escapes
+    pub max_string_length: Option<usize>,
+    // Per object
+    pub max_members: Option<usize>,
+    // Per array
+    pub max_elements: Option<usize>,
+    // In characters
+    pub max_number_length: Option<usize>,
+    // Values of any type in the whole input
+    pub max_nodes: Option<usize>,
+}
+
+// The same as serde_json
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_input_size: None,
+            max_string_length: None,
+            max_members: None,
+            max_elements: None,
+            max_number_length: None,
+            max_nodes: None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Tokenizer<Iter: Iterator<Item = char>> {
     iter: Peekable<Iter>,
     col: usize,
     line: usize,
+    // Byte offset of the next character
+    pos: usize,
+    options: Options,
+    // Errors found while skipping comments, as that can't fail by itself
+    comment_error: Option<ParsingError>,
+    // Values at the top level of a stream can be followed by anything, as that
+    // is the start of the next one
+    pub(crate) top_level_stream: bool,
+    // Kept here for the limits, as the parser has no state of its own
+    depth: usize,
+    nodes: usize,
 }
 
 impl<'a> Tokenizer<Chars<'a>> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(str: &'a str) -> Self {
         Self::from_iter(str.chars())
     }
 }
 
-#[derive(Debug)]
-pub(crate) enum ParsingErrorKind {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsingErrorKind {
     InvalidTrailingComma,
     MissingEndingComma,
     UnsupportedToken,
@@ -52,25 +130,75 @@ pub(crate) enum ParsingErrorKind {
     InvalidStartingToken,
     InvalidIdentInArray,
     InvalidToken,
+    // A `/` that doesn't start a comment
+    InvalidComment,
+    UnterminatedComment,
+    UnterminatedString,
+    // See `Limits`
+    DepthLimitExceeded,
+    InputTooLarge,
+    StringTooLong,
+    TooManyMembers,
+    TooManyElements,
+    NumberTooLong,
+    TooManyNodes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParsingError {
     pub(crate) kind: ParsingErrorKind,
     pub(crate) loc: Loc,
 }
 
+impl ParsingError {
+    pub fn kind(&self) -> ParsingErrorKind {
+        self.kind
+    }
+
+    pub fn loc(&self) -> &Loc {
+        &self.loc
+    }
+}
+
+impl core::fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:?} at line {}, column {}",
+            self.kind, self.loc.line, self.loc.col
+        )
+    }
+}
+
+impl std::error::Error for ParsingError {}
+
 type Result = std::result::Result<Token, ParsingError>;
 
 impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_iter(iter: Iter) -> Self {
         Self {
             iter: iter.peekable(),
             col: 0,
             line: 1,
+            pos: 0,
+            options: Options::default(),
+            comment_error: None,
+            top_level_stream: false,
+            depth: 0,
+            nodes: 0,
         }
     }
 
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
     fn cur_loc(&self) -> Loc {
         Loc {
             col: self.col,
@@ -78,9 +206,67 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
         }
     }
 
+    // Fails with `kind` if `len` is over `limit`
+    pub(crate) fn check_limit(
+        &self,
+        len: usize,
+        limit: Option<usize>,
+        kind: ParsingErrorKind,
+        loc: &Loc,
+    ) -> std::result::Result<(), ParsingError> {
+        match limit {
+            Some(limit) if len > limit => Err(ParsingError {
+                kind,
+                loc: loc.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_input_size(&self) -> std::result::Result<(), ParsingError> {
+        let limit = self.options.limits.max_input_size;
+        self.check_limit(
+            self.pos,
+            limit,
+            ParsingErrorKind::InputTooLarge,
+            &self.cur_loc(),
+        )
+    }
+
+    // Counts a value towards `Limits::max_nodes`
+    pub(crate) fn add_node(&mut self) -> std::result::Result<(), ParsingError> {
+        self.nodes += 1;
+        let limit = self.options.limits.max_nodes;
+        self.check_limit(
+            self.nodes,
+            limit,
+            ParsingErrorKind::TooManyNodes,
+            &self.cur_loc(),
+        )
+    }
+
+    // Called after the opening bracket of an array or object
+    pub(crate) fn enter(&mut self) -> std::result::Result<(), ParsingError> {
+        self.depth += 1;
+        let limit = self.options.limits.max_depth;
+        self.check_limit(
+            self.depth,
+            limit,
+            ParsingErrorKind::DepthLimitExceeded,
+            &self.cur_loc(),
+        )?;
+        self.add_node()
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
     fn tokenize_val(&mut self, text: String, loc: Loc) -> Result {
         use ParsingErrorKind::*;
-        if let Some(',' | '}' | ']') = self.peek() {
+        // The end of the input is allowed for values at the top level
+        let top_level_stream = self.top_level_stream;
+        if top_level_stream || matches!(self.peek(), Some(',' | '}' | ']') | None) {
             Ok(Token {
                 kind: TokenKind::Val(text),
                 loc,
@@ -95,12 +281,17 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
 
     pub fn next_token(&mut self) -> Result {
         self.skip_whitespace();
+        if let Some(err) = self.comment_error.take() {
+            return Err(err);
+        }
+        self.check_input_size()?;
+        let limits = self.options.limits;
         let mut text = String::new();
 
         use ParsingErrorKind::*;
         match self.iter.next() {
             Some(c) => {
-                self.col += 1;
+                self.advance_loc(c);
                 match c {
                     '{' => Ok(Token {
                         kind: TokenKind::OpenBracket,
@@ -124,7 +315,8 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
                     }),
                     ',' => {
                         let loc = self.cur_loc();
-                        if let Some('}' | ']') = self.peek() {
+                        let trailing_commas = self.options.trailing_commas || self.options.json5;
+                        if !trailing_commas && matches!(self.peek(), Some('}' | ']')) {
                             Err(ParsingError {
                                 kind: InvalidTrailingComma,
                                 loc,
@@ -137,19 +329,38 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
                         }
                     }
                     // Strings, can be Identifiers or Values
-                    '"' => {
+                    '"' | '\'' if c == '"' || self.options.json5 => {
+                        let quote = c;
                         let loc = self.cur_loc();
                         let mut was_escape = false;
-                        while let Some(c) = self.iter.next_if(|c| match *c {
-                            '"' => was_escape,
-                            _ => true,
-                        }) {
-                            self.col += 1;
-                            was_escape = c == '\\';
+                        while let Some(c) = self.iter.next_if(|c| *c != quote || was_escape) {
+                            self.advance_loc(c);
+                            was_escape = !was_escape && c == '\\';
                             text.push(c);
+                            // Checked as it goes, so huge strings aren't read whole
+                            self.check_limit(
+                                text.len(),
+                                limits.max_string_length,
+                                StringTooLong,
+                                &loc,
+                            )?;
+                            self.check_input_size()?;
                         }
-                        if self.iter.next_if(|c| *c == '"').is_some() {
-                            self.col += 1;
+                        if self.iter.next_if(|c| *c == quote).is_some() {
+                            self.advance_loc(quote);
+                            if self.options.json5 {
+                                text = json5::normalize_string(&text, quote).ok_or_else(|| {
+                                    ParsingError {
+                                        kind: InvalidToken,
+                                        loc: loc.clone(),
+                                    }
+                                })?;
+                            } else if !is_valid_string(&text) {
+                                return Err(ParsingError {
+                                    kind: InvalidToken,
+                                    loc,
+                                });
+                            }
                             if let Some(':') = self.peek() {
                                 Ok(Token {
                                     kind: TokenKind::Ident(text),
@@ -164,29 +375,71 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
                                 })
                             }
                         } else {
-                            unreachable!("Text: {text}")
+                            Err(ParsingError {
+                                kind: UnterminatedString,
+                                loc,
+                            })
                         }
                     }
                     // Numbers
                     '0'..='9' => {
                         text.push(c);
                         let loc = self.cur_loc();
-                        while let Some(c) = self.iter.next_if(is_num_char) {
-                            self.col += 1;
+                        // JSON5 also has hexadecimal numbers
+                        let json5 = self.options.json5;
+                        while let Some(c) = self
+                            .iter
+                            .next_if(|c| is_num_char(c) || (json5 && c.is_ascii_alphanumeric()))
+                        {
+                            self.advance_loc(c);
                             text.push(c);
+                            self.check_limit(
+                                text.len(),
+                                limits.max_number_length,
+                                NumberTooLong,
+                                &loc,
+                            )?;
+                            self.check_input_size()?;
                         }
                         self.tokenize_val(text, loc)
                     }
+                    // Unquoted keys in JSON5, and values like `true` or `Infinity`
+                    c if self.options.json5 && json5::is_ident_start(c) => {
+                        text.push(c);
+                        let loc = self.cur_loc();
+                        while let Some(c) = self.iter.next_if(|c| json5::is_ident_char(*c)) {
+                            self.advance_loc(c);
+                            text.push(c);
+                        }
+                        if let Some(':') = self.peek() {
+                            Ok(Token {
+                                kind: TokenKind::Ident(text),
+                                loc,
+                            })
+                        } else {
+                            self.tokenize_val(text, loc)
+                        }
+                    }
                     // Cases like `null` or `true`
                     c => {
                         text.push(c);
                         if c.is_ascii() {
                             let loc = self.cur_loc();
-                            while let Some(c) =
-                                self.next_if(|c| *c != ',' && *c != '}' && *c != ']')
-                            {
-                                self.col += 1;
+                            while let Some(c) = self.iter.next_if(|c| {
+                                c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')
+                            }) {
+                                self.advance_loc(c);
                                 text.push(c);
+                                // Also negative numbers, like `-1`
+                                if text.starts_with('-') {
+                                    self.check_limit(
+                                        text.len(),
+                                        limits.max_number_length,
+                                        NumberTooLong,
+                                        &loc,
+                                    )?;
+                                }
+                                self.check_input_size()?;
                             }
                             self.tokenize_val(text, loc)
                         } else {
@@ -205,6 +458,18 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
         }
     }
 
+    // Skips whitespace and RFC 7464 record separators between the values of a
+    // stream, returning the byte offset of the next value
+    pub(crate) fn skip_to_next_value(&mut self) -> usize {
+        loop {
+            self.skip_whitespace();
+            if self.iter.next_if_eq(&'\u{1e}').is_none() {
+                break self.pos;
+            }
+            self.advance_loc('\u{1e}');
+        }
+    }
+
     pub(crate) fn expect_token(&mut self, kind: TokenKind) -> Result {
         match self.next_token() {
             Ok(token) => {
@@ -226,16 +491,25 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
         self.iter.peek()
     }
 
-    fn next_if(&mut self, pred: impl FnOnce(&char) -> bool) -> Option<char> {
-        self.skip_whitespace();
-        self.iter.next_if(pred)
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.skip_whitespace_only();
+            let comments = self.options.comments || self.options.json5;
+            if !comments || self.iter.next_if_eq(&'/').is_none() {
+                break;
+            }
+            self.skip_comment();
+        }
     }
 
-    fn skip_whitespace(&mut self) {
+    fn skip_whitespace_only(&mut self) {
+        // JSON only has four whitespace characters, JSON5 has all of Unicode's
+        let json5 = self.options.json5;
         while self
             .iter
             .next_if(|c| {
-                if c.is_whitespace() {
+                if (json5 && c.is_whitespace()) || matches!(c, ' ' | '\t' | '\n' | '\r') {
+                    self.pos += c.len_utf8();
                     if *c == '\n' {
                         self.col = 0;
                         self.line += 1;
@@ -250,9 +524,86 @@ impl<Iter: Iterator<Item = char>> Tokenizer<Iter> {
             .is_some()
         {}
     }
+
+    // Skips a comment, after its starting `/`
+    fn skip_comment(&mut self) {
+        self.advance_loc('/');
+        let loc = self.cur_loc();
+        match self.iter.next() {
+            Some('/') => {
+                self.advance_loc('/');
+                while let Some(c) = self.iter.next_if(|c| *c != '\n') {
+                    self.advance_loc(c);
+                }
+            }
+            Some('*') => {
+                self.advance_loc('*');
+                let mut was_star = false;
+                loop {
+                    match self.iter.next() {
+                        Some(c) => {
+                            self.advance_loc(c);
+                            if was_star && c == '/' {
+                                break;
+                            }
+                            was_star = c == '*';
+                        }
+                        None => {
+                            self.comment_error = Some(ParsingError {
+                                kind: ParsingErrorKind::UnterminatedComment,
+                                loc,
+                            });
+                            break;
+                        }
+                    }
+                }
+            }
+            c => {
+                if let Some(c) = c {
+                    self.advance_loc(c);
+                }
+                self.comment_error = Some(ParsingError {
+                    kind: ParsingErrorKind::InvalidComment,
+                    loc,
+                });
+            }
+        }
+    }
+
+    fn advance_loc(&mut self, c: char) {
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.col = 0;
+            self.line += 1;
+        } else {
+            self.col += 1;
+        }
+    }
+}
+
+// Checks the contents of a string for escapes JSON doesn't have and unescaped
+// control characters
+fn is_valid_string(text: &str) -> bool {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {}
+                Some('u') => {
+                    if (0..4).any(|_| !chars.next().is_some_and(|c| c.is_ascii_hexdigit())) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            },
+            '\u{0}'..='\u{1f}' => return false,
+            _ => {}
+        }
+    }
+    true
 }
 
 pub(crate) fn is_num_char(c: &char) -> bool {
-    (!c.is_alphabetic() || c.to_lowercase().next().unwrap() == 'e')
+    (!c.is_alphabetic() || c.eq_ignore_ascii_case(&'e'))
         && (c.is_ascii_alphanumeric() || *c == '.' || *c == '-' || *c == '+')
 }
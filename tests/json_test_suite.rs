@@ -0,0 +1,87 @@
+use jsonparser::parser::parse;
+use jsonparser::tokenizer::Tokenizer;
+use std::panic::catch_unwind;
+use std::path::Path;
+
+// Runs the cases of JSONTestSuite (https://github.com/nst/JSONTestSuite) in
+// `tests/test_parsing`. Files starting with `y_` must be accepted, `n_` must be
+// rejected, and `i_` can go either way, as long as nothing panics. Run with
+// `--nocapture` to see the table.
+
+#[derive(Default)]
+struct Counts {
+    accepted: usize,
+    rejected: usize,
+    panicked: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Outcome {
+    Accepted,
+    Rejected,
+    Panicked,
+}
+
+fn run(data: &[u8]) -> Outcome {
+    // The parser only takes text, so anything that isn't UTF-8 is rejected
+    // before getting to it
+    let Ok(input) = std::str::from_utf8(data) else {
+        return Outcome::Rejected;
+    };
+    // With the default options, so the deepest cases hit the default depth limit
+    match catch_unwind(|| parse(Tokenizer::from_str(input))) {
+        Ok(Ok(_)) => Outcome::Accepted,
+        Ok(Err(_)) => Outcome::Rejected,
+        Err(_) => Outcome::Panicked,
+    }
+}
+
+#[test]
+fn json_test_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_parsing");
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .expect("Expected the test suite")
+        .map(|entry| entry.expect("Expected a file").path())
+        .collect();
+    paths.sort();
+
+    let mut counts: [Counts; 3] = Default::default();
+    let mut failures = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let data = std::fs::read(&path).expect("Expected a readable file");
+        let outcome = run(&data);
+        let (i, expected) = match &name[..2] {
+            "y_" => (0, Some(Outcome::Accepted)),
+            "n_" => (1, Some(Outcome::Rejected)),
+            "i_" => (2, None),
+            _ => continue,
+        };
+        match outcome {
+            Outcome::Accepted => counts[i].accepted += 1,
+            Outcome::Rejected => counts[i].rejected += 1,
+            Outcome::Panicked => counts[i].panicked += 1,
+        }
+        let failed = matches!(
+            (expected, outcome),
+            (_, Outcome::Panicked)
+                | (Some(Outcome::Accepted), Outcome::Rejected)
+                | (Some(Outcome::Rejected), Outcome::Accepted)
+        );
+        if failed {
+            failures.push(name.into_owned());
+        }
+    }
+
+    println!(
+        "{:<8} {:>8} {:>8} {:>8}",
+        "", "accepted", "rejected", "panicked"
+    );
+    for (kind, counts) in ["y_", "n_", "i_"].iter().zip(&counts) {
+        println!(
+            "{:<8} {:>8} {:>8} {:>8}",
+            kind, counts.accepted, counts.rejected, counts.panicked
+        );
+    }
+    assert!(failures.is_empty(), "Failed cases: {:#?}", failures);
+}